@@ -2,14 +2,20 @@ pub mod network_transform;
 pub mod network_rigidbody;
 pub mod network_character_controller;
 pub mod network_movement;
+pub mod correction_smoothing;
 
 pub use network_transform::*;
 pub use network_rigidbody::*;
 pub use network_character_controller::*;
 pub use network_movement::*;
+pub use correction_smoothing::*;
 
 use serde::{Serialize, de::DeserializeOwned};
-use bevy::prelude::*;
+use bevy::{
+    ecs::system::SystemChangeTick,
+    prelude::*
+};
+use bevy_replicon::server::server_tick::ServerTick;
 use crate::prelude::*;
 
 #[derive(Bundle)]
@@ -27,11 +33,12 @@ where T: NetworkTranslation + Serialize + DeserializeOwned + Default + Copy {
         init: Vec3,
         axis: TranslationAxis, 
         tick: u32,
+        timestamp: f64,
         max_size: usize
     ) -> anyhow::Result<Self> {
         let mut snaps = ComponentSnapshots::with_capacity(max_size);
         let translation = T::from_vec3(init, axis);
-        snaps.insert(translation, tick)?;
+        snaps.insert(translation, tick, timestamp)?;
         
         Ok(Self{ 
             translation, 
@@ -56,11 +63,12 @@ where R: NetworkRotation + Serialize + DeserializeOwned + Default + Copy {
         init: Quat, 
         axis: RotationAxis,
         tick: u32,
+        timestamp: f64,
         max_size: usize
     ) -> anyhow::Result<Self> {
         let mut snaps = ComponentSnapshots::with_capacity(max_size);
         let rotation = R::from_quat(init, axis);
-        snaps.insert(rotation, tick)?;
+        snaps.insert(rotation, tick, timestamp)?;
         
         Ok(Self{ 
             rotation, 
@@ -94,130 +102,262 @@ where R: NetworkRotation {
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn apply_transform_translation_system<T>(
     mut query: Query<
         (&Transform, &mut T), 
-        Changed<Transform>
+        (Changed<Transform>, Without<NetworkLod>)
     >,
-    axis: Res<TransformAxis>
+    mut lod_query: Query<(
+        Entity, 
+        Ref<Transform>, 
+        &mut T, 
+        &mut ComponentSnapshots<T>, 
+        &NetworkLod
+    )>,
+    axis: Res<TransformAxis>,
+    server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>,
+    ticks: SystemChangeTick
 )
 where T: NetworkTranslation {
     for (transform, mut t) in query.iter_mut() {
         *t = T::from_vec3(transform.translation, axis.translation);
     }
+
+    let tick = server_tick.get();
+    for (e, transform, mut t, mut snaps, lod) in lod_query.iter_mut() {
+        // change is kept until next due tick
+        if !transform.last_changed().is_newer_than(t.last_changed(), ticks.this_run()) {
+            continue;
+        }
+
+        let translation = T::from_vec3(transform.translation, axis.translation);
+        if lod.is_due(e, tick) {
+            // history is recorded on change
+            *t = translation;
+            continue;
+        }
+
+        // only replication is throttled, history is kept every tick
+        if transform.is_changed() {
+            record_snapshot(&mut snaps, translation, tick, &clock);
+        }
+    }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn apply_transform_rotation_system<R>(
     mut query: Query<
         (&Transform, &mut R),
-        Changed<Transform>
+        (Changed<Transform>, Without<NetworkLod>)
     >,
-    axis: Res<TransformAxis>
+    mut lod_query: Query<(
+        Entity, 
+        Ref<Transform>, 
+        &mut R, 
+        &mut ComponentSnapshots<R>, 
+        &NetworkLod
+    )>,
+    axis: Res<TransformAxis>,
+    server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>,
+    ticks: SystemChangeTick
 )
 where R: NetworkRotation {
     for (transform, mut r) in query.iter_mut() {
         *r = R::from_quat(transform.rotation, axis.rotation);
     } 
+
+    let tick = server_tick.get();
+    for (e, transform, mut r, mut snaps, lod) in lod_query.iter_mut() {
+        if !transform.last_changed().is_newer_than(r.last_changed(), ticks.this_run()) {
+            continue;
+        }
+
+        let rotation = R::from_quat(transform.rotation, axis.rotation);
+        if lod.is_due(e, tick) {
+            *r = rotation;
+            continue;
+        }
+
+        if transform.is_changed() {
+            record_snapshot(&mut snaps, rotation, tick, &clock);
+        }
+    }
 }
 
+#[inline]
+fn record_snapshot<C: Component + Clone>(
+    snaps: &mut ComponentSnapshots<C>,
+    component: C,
+    tick: u32,
+    clock: &NetworkClock
+) {
+    let timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    if let Err(e) = snaps.insert(component, tick, timestamp) {
+        warn!("discarding: {e}");
+    }
+}
+
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn apply_network_translation_system<T>(
     mut query: Query<(
         &mut Transform,
         &T, 
         &mut ComponentSnapshots<T>,
+        Option<&mut VisualTransform>
     ), 
         Without<Owning>
     >,
     axis: Res<TransformAxis>,
-    config: Res<InterpolationConfig>
+    config: Res<InterpolationConfig>,
+    hermite: Option<Res<HermiteInterpolation<T>>>,
+    estimate: Option<Res<ServerTickEstimate>>,
+    render_delay: Option<Res<RenderDelay>>,
+    clock: Res<NetworkClock>
 ) 
 where T: NetworkTranslation {
-    for (mut transform, net_trans, mut trans_snaps) in query.iter_mut() {
-        const REQUIRED: usize = 2;
-        
-        trans_snaps.sort_frontier_by_timestamp();
-        let trans = match linear_interpolate_by_time(
-            &trans_snaps,
-            config.network_tick_delta
-        ) {
-            Ok(t_op) => {
-                match t_op {
-                    Some(t) => t.to_vec3(axis.translation),
-                    None => transform.translation
-                }
-            }
-            Err(e) => {
-                if cfg!(debug_assertions) {
-                    panic!("error on translation interpolation: {e}");
-                } else {
-                    error!("error on translation interpolation: {e}");
-                    trans_snaps.cache();
-                    net_trans.to_vec3(axis.translation)
-                }
-            }
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let interpolation = FrameInterpolation::new(
+        &config, 
+        hermite.as_deref(), 
+        estimate.as_deref(), 
+        render_delay.as_deref(), 
+        now
+    );
+
+    for (
+        mut transform, 
+        net_trans, 
+        mut trans_snaps, 
+        visual_transform
+    ) in query.iter_mut() {
+        let current = match visual_transform {
+            Some(ref v) => v.translation(),
+            None => transform.translation
+        };
+        let trans = match interpolation.apply(&mut trans_snaps, net_trans) {
+            Some(t) => t.to_vec3(axis.translation),
+            None => current
         };
 
-        let trans_len = trans_snaps.frontier_len();
-        if trans_len > REQUIRED {
-            trans_snaps.cache_n(trans_len - REQUIRED);
+        match visual_transform {
+            // collider stays at the latest authoritative translation
+            Some(mut v) => {
+                v.set_translation(trans);
+                transform.translation = net_trans.to_vec3(axis.translation);
+            }
+            None => transform.translation = trans
         }
-        transform.translation = trans;
     }
 }
 
+#[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn apply_network_rotation_system<R>(
     mut query: Query<(
         &mut Transform,
         &R, 
         &mut ComponentSnapshots<R>,
+        Option<&mut VisualTransform>
     ), 
         Without<Owning>
     >,
     axis: Res<TransformAxis>,
-    config: Res<InterpolationConfig>
+    config: Res<InterpolationConfig>,
+    hermite: Option<Res<HermiteInterpolation<R>>>,
+    estimate: Option<Res<ServerTickEstimate>>,
+    render_delay: Option<Res<RenderDelay>>,
+    clock: Res<NetworkClock>
 )
 where R: NetworkRotation {
-    for (mut transform, net_rot, mut rot_snaps) in query.iter_mut() {
-        const REQUIRED: usize = 2;
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let interpolation = FrameInterpolation::new(
+        &config, 
+        hermite.as_deref(), 
+        estimate.as_deref(), 
+        render_delay.as_deref(), 
+        now
+    );
 
-        rot_snaps.sort_frontier_by_timestamp();
-        let rot = match linear_interpolate_by_time(
-            &rot_snaps,
-            config.network_tick_delta
-        ) {
-            Ok(r_op) => {
-                match r_op {
-                    Some(r) => r.to_quat(axis.rotation),
-                    None => transform.rotation
-                }
-            }
-            Err(e) => {
-                if cfg!(debug_assertions) {
-                    panic!("error on rotation interpolation: {e}");
-                } else {
-                    error!("error on rotation interpolation: {e}");
-                    net_rot.to_quat(axis.rotation)
-                }
-            }
+    for (
+        mut transform, 
+        net_rot, 
+        mut rot_snaps, 
+        visual_transform
+    ) in query.iter_mut() {
+        let current = match visual_transform {
+            Some(ref v) => v.rotation(),
+            None => transform.rotation
+        };
+        let rot = match interpolation.apply(&mut rot_snaps, net_rot) {
+            Some(r) => r.to_quat(axis.rotation),
+            None => current
         };
 
-        let rot_len = rot_snaps.frontier_len();
-        if rot_len > REQUIRED {
-            rot_snaps.cache_n(rot_len - REQUIRED);
+        match visual_transform {
+            Some(mut v) => {
+                v.set_rotation(rot);
+                transform.rotation = net_rot.to_quat(axis.rotation);
+            }
+            None => transform.rotation = rot
         }
-        transform.rotation = rot;
     }
 }
 
+/// inputs made before client received the teleport
+/// still carry the translation before teleport
+#[inline]
+fn is_before_teleport_known(
+    teleport: Option<&TeleportCounter>,
+    clock_sync: Option<&ClockSyncMap>,
+    client_id: ClientId,
+    timestamp: f64,
+    network_tick_delta: f64
+) -> bool {
+    let teleport = match teleport {
+        Some(t) if t.count() > 0 => t,
+        _ => return false
+    };
+    let rtt = clock_sync.and_then(|c| c.get(&client_id))
+    .map(|s| s.rtt())
+    .unwrap_or(0.0);
+
+    timestamp < teleport.timestamp() + rtt * 0.5 + network_tick_delta
+}
+
 pub(crate) fn correct_translation_error_system<T, E>(
     mut query: Query<(
         &NetworkEntity,
         &mut ComponentSnapshots<T>, 
         &mut PredioctionError<T>,
-        &mut EventSnapshots<E>
+        &mut EventSnapshots<E>,
+        Option<&TeleportCounter>
     )>,
     axis: Res<TransformAxis>,
     thresholds: Res<PredictionConfig>,
+    interpolation_config: Res<InterpolationConfig>,
+    clock_sync: Option<Res<ClockSyncMap>>,
     mut trans_force_repl: EventWriter<CorrectTranslation<T>>
 )
 where 
@@ -226,7 +366,8 @@ E: NetworkMovement {
     for (net_e,
         mut trans_snaps, 
         mut trans_pred_err,
-        mut movements
+        mut movements,
+        teleport
     ) in query.iter_mut() {
         trans_snaps.cache();
         
@@ -237,46 +378,51 @@ E: NetworkMovement {
         movements.sort_frontier_by_index();
         
         // frontier is not empty
-        let first = movements.frontier_front()
-        .unwrap()
-        .event();
-        let first_timestamp = first.timestamp();
+        let first_snap = movements.frontier_front()
+        .unwrap();
+        let first = first_snap.event();
+        // converted into server clock and rewind window on receive
+        let first_timestamp = first_snap.timestamp();
 
-        let trans_cache = trans_snaps.cache_ref();
-        let trans_idx = match trans_cache.iter()
-        .rposition(|s| 
-            s.timestamp() <= first_timestamp
+        if is_before_teleport_known(
+            teleport, 
+            clock_sync.as_deref(), 
+            net_e.client_id(), 
+            first_timestamp, 
+            interpolation_config.network_tick_delta
         ) {
-            Some(idx) => idx,
+            trans_pred_err.reset_count();
+            continue;
+        }
+
+        let server_translation = match trans_snaps.latest_before(first_timestamp) {
+            Some(s) => s.component().to_vec3(axis.translation),
             None => {
-                if cfg!(debug_assertions) {
-                    panic!("could not find snapshot for timestamp: {first_timestamp}");
-                } else {
-                    error!(
-                        "could not find snapshot for timestamp: {}, skipping update",
-                        first_timestamp
-                    );
-                    continue;
-                }
+                warn!(
+                    "could not find snapshot for timestamp: {}, skipping update",
+                    first_timestamp
+                );
+                continue;
             }
         };
 
-        // get by found index
-        let server_translation = trans_cache.get(trans_idx)
-        .unwrap()
-        .component()
-        .to_vec3(axis.translation);
         let client_translation = first.current_translation(axis.translation);
 
         let trans_err = server_translation.distance_squared(client_translation);
         if trans_err > thresholds.translation_threshold {
             trans_pred_err.increment_count();
             if trans_pred_err.get_count() > thresholds.force_replicate_error_count {
-                warn!("sending translation force replication for: {:?}", net_e.client_id());
-                trans_force_repl.send(ToClients{ 
-                    mode: SendMode::Direct(net_e.client_id()), 
-                    event: default()
-                });
+                // cached above, latest is current authoritative translation
+                if let Some(latest) = trans_snaps.latest() {
+                    warn!("sending translation force replication for: {:?}", net_e.client_id());
+                    trans_force_repl.send(ToClients{ 
+                        mode: SendMode::Direct(net_e.client_id()), 
+                        event: ForceReplicateTranslation::new(
+                            latest.component().clone(), 
+                            latest.tick()
+                        )
+                    });
+                }
 
                 trans_pred_err.reset_count();
             }
@@ -291,10 +437,13 @@ pub(crate) fn correct_rotation_error_system<R, E>(
         &NetworkEntity,
         &mut ComponentSnapshots<R>, 
         &mut PredioctionError<R>,
-        &mut EventSnapshots<E>
+        &mut EventSnapshots<E>,
+        Option<&TeleportCounter>
     )>,
     axis: Res<TransformAxis>,
     thresholds: Res<PredictionConfig>,
+    interpolation_config: Res<InterpolationConfig>,
+    clock_sync: Option<Res<ClockSyncMap>>,
     mut rot_force_repl: EventWriter<CorrectRotation<R>>
 )
 where 
@@ -304,7 +453,8 @@ E: NetworkMovement {
         net_e,
         mut rot_snaps, 
         mut rot_pred_err, 
-        mut movements
+        mut movements,
+        teleport
     ) in query.iter_mut() {
         rot_snaps.cache();
         
@@ -315,35 +465,34 @@ E: NetworkMovement {
         movements.sort_frontier_by_index();
         
         // frontier is not empty
-        let first = movements.frontier_front()
-        .unwrap()
-        .event();
-        let first_timestamp = first.timestamp();
+        let first_snap = movements.frontier_front()
+        .unwrap();
+        let first = first_snap.event();
+        // converted into server clock and rewind window on receive
+        let first_timestamp = first_snap.timestamp();
 
-        let rot_cache = rot_snaps.cache_ref();
-        let rot_idx = match rot_cache.iter()
-        .rposition(|s| 
-            s.timestamp() <= first_timestamp
+        if is_before_teleport_known(
+            teleport, 
+            clock_sync.as_deref(), 
+            net_e.client_id(), 
+            first_timestamp, 
+            interpolation_config.network_tick_delta
         ) {
-            Some(idx) => idx,
+            rot_pred_err.reset_count();
+            continue;
+        }
+
+        let server_rotation = match rot_snaps.latest_before(first_timestamp) {
+            Some(s) => s.component().to_quat(axis.rotation),
             None => {
-                if cfg!(debug_assertions) {
-                    panic!("could not find snapshot for timestamp: {first_timestamp}");
-                } else {
-                    error!(
-                        "could not find snapshot for timestamp: {}, skipping update",
-                        first_timestamp
-                    );
-                    continue;
-                }
+                warn!(
+                    "could not find snapshot for timestamp: {}, skipping update",
+                    first_timestamp
+                );
+                continue;
             }
         };
 
-        // get by found index
-        let server_rotation = rot_cache.get(rot_idx)
-        .unwrap()
-        .component()
-        .to_quat(axis.rotation);
         let client_rotation = first.current_rotation(axis.rotation);
         if client_rotation.length_squared() == 0.0 {
             warn!("client rotation length is zero, skipping update");
@@ -356,11 +505,17 @@ E: NetworkMovement {
         if rot_err > thresholds.rotation_threshold {
             rot_pred_err.increment_count();
             if rot_pred_err.get_count() > thresholds.force_replicate_error_count {
-                warn!("sending rotation force replication for: {:?}", net_e.client_id());
-                rot_force_repl.send(ToClients{
-                    mode: SendMode::Direct(net_e.client_id()),
-                    event: default()
-                });
+                // cached above, latest is current authoritative rotation
+                if let Some(latest) = rot_snaps.latest() {
+                    warn!("sending rotation force replication for: {:?}", net_e.client_id());
+                    rot_force_repl.send(ToClients{
+                        mode: SendMode::Direct(net_e.client_id()),
+                        event: ForceReplicateRotation::new(
+                            latest.component().clone(), 
+                            latest.tick()
+                        )
+                    });
+                }
 
                 rot_pred_err.reset_count();    
             }
@@ -370,30 +525,469 @@ E: NetworkMovement {
     } 
 }
 
+#[allow(clippy::type_complexity)]
+pub(crate) fn teleport_system<T, R, E>(
+    mut query: Query<(
+        &NetworkEntity,
+        &mut Transform,
+        &mut TeleportCounter,
+        Option<&mut PredioctionError<T>>,
+        Option<&mut PredioctionError<R>>,
+        Option<&mut EventSnapshots<E>>
+    )>,
+    mut teleports: EventReader<Teleport>,
+    mut notifications: EventWriter<ToClients<Teleported<E>>>,
+    clock: Res<NetworkClock>
+)
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement {
+    for teleport in teleports.read() {
+        let (
+            net_e,
+            mut transform,
+            mut counter,
+            trans_pred_err,
+            rot_pred_err,
+            movements
+        ) = match query.get_mut(teleport.entity) {
+            Ok(q) => q,
+            Err(e) => {
+                warn!("could not teleport: {:?}: {e}", teleport.entity);
+                continue;
+            }
+        };
+
+        let timestamp = match clock.now() {
+            Ok(t) => t,
+            Err(e) => {
+                error!("could not get timestamp: {e}");
+                continue;
+            }
+        };
+
+        transform.translation = teleport.translation;
+        if let Some(rotation) = teleport.rotation {
+            transform.rotation = rotation;
+        }
+        counter.increment(timestamp);
+
+        if let Some(mut e) = trans_pred_err {
+            e.reset_count();
+        }
+        if let Some(mut e) = rot_pred_err {
+            e.reset_count();
+        }
+
+        // inputs received so far are made at the old translation
+        let index = match movements {
+            Some(mut m) => {
+                let index = m.latest_index();
+                if let Some(i) = index {
+                    m.discard_until(i);
+                }
+                index
+            }
+            None => None
+        };
+
+        notifications.send(ToClients {
+            mode: SendMode::Direct(net_e.client_id()),
+            event: Teleported::new(teleport.entity, index, counter.count())
+        });
+        info!(
+            "teleported: {:?} to: {} count: {}",
+            net_e.client_id(),
+            teleport.translation,
+            counter.count()
+        );
+    }
+}
+
+pub(crate) fn handle_teleported<E: NetworkMovement>(
+    mut query: Query<(
+        &mut EventSnapshots<E>,
+        Option<&mut CorrectionSmoothing>
+    ),
+        With<Owning>
+    >,
+    mut notifications: EventReader<Teleported<E>>
+) {
+    for teleported in notifications.read() {
+        let (mut movements, smoothing) = match query.get_mut(teleported.entity) {
+            Ok(q) => q,
+            Err(e) => {
+                warn!("could not find teleported: {:?}: {e}", teleported.entity);
+                continue;
+            }
+        };
+
+        // not to be replayed on top of the new translation
+        if let Some(index) = teleported.index {
+            movements.discard_until(index);
+        }
+        if let Some(mut s) = smoothing {
+            s.clear();
+        }
+        debug!(
+            "teleport: {} discarded inputs up to: {:?}",
+            teleported.count,
+            teleported.index
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn handle_teleport_translation<T>(
+    mut query: Query<(
+        &mut Transform,
+        &T,
+        &mut ComponentSnapshots<T>,
+        Ref<TeleportCounter>,
+        Option<&mut CorrectionSmoothing>,
+        Option<&mut VisualTransform>
+    ), 
+        Changed<TeleportCounter>
+    >,
+    axis: Res<TransformAxis>
+)
+where T: NetworkTranslation {
+    for (
+        mut transform, 
+        t, 
+        mut trans_snaps, 
+        counter, 
+        smoothing, 
+        visual_transform
+    ) in query.iter_mut() {
+        if counter.is_added() {
+            continue;
+        }
+
+        trans_snaps.reset_to_latest();
+        transform.translation = t.to_vec3(axis.translation);
+        if let Some(mut s) = smoothing {
+            s.clear();
+        }
+        if let Some(mut v) = visual_transform {
+            v.set_translation(transform.translation);
+        }
+        debug!("teleported to: {}", transform.translation);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn handle_teleport_rotation<R>(
+    mut query: Query<(
+        &mut Transform,
+        &R,
+        &mut ComponentSnapshots<R>,
+        Ref<TeleportCounter>,
+        Option<&mut CorrectionSmoothing>,
+        Option<&mut VisualTransform>
+    ), 
+        Changed<TeleportCounter>
+    >,
+    axis: Res<TransformAxis>
+)
+where R: NetworkRotation {
+    for (
+        mut transform, 
+        r, 
+        mut rot_snaps, 
+        counter, 
+        smoothing, 
+        visual_transform
+    ) in query.iter_mut() {
+        if counter.is_added() {
+            continue;
+        }
+
+        rot_snaps.reset_to_latest();
+        transform.rotation = r.to_quat(axis.rotation);
+        if let Some(mut s) = smoothing {
+            s.clear();
+        }
+        if let Some(mut v) = visual_transform {
+            v.set_rotation(transform.rotation);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub(crate) fn handle_correct_translation<T>(
-    mut query: Query<(&mut Transform, &T), With<Owning>>,
+    mut query: Query<(
+        &mut Transform, 
+        &mut ComponentSnapshots<T>,
+        Option<&mut CorrectionSmoothing>
+    ), 
+        With<Owning>
+    >,
     mut force_replication: EventReader<ForceReplicateTranslation<T>>,
-    axis: Res<TransformAxis>
+    axis: Res<TransformAxis>,
+    clock: Res<NetworkClock>
 )
 where T: NetworkTranslation {
-    for _ in force_replication.read() {
-        if let Ok((mut transform, net_translation)) = query.get_single_mut() {
-            transform.translation = net_translation.to_vec3(axis.translation);
-            warn!("force replicated translation");
+    for correction in force_replication.read() {
+        let (mut transform, mut trans_snaps, smoothing) = match query.get_single_mut() {
+            Ok(q) => q,
+            Err(_) => continue
+        };
+
+        let timestamp = match clock.now() {
+            Ok(t) => t,
+            Err(e) => {
+                error!("could not get timestamp: {e}");
+                continue;
+            }
+        };
+
+        // fails if newer state is already replicated
+        if let Err(e) = trans_snaps.insert(
+            correction.translation().clone(), 
+            correction.tick(), 
+            timestamp
+        ) {
+            warn!("discarding translation correction: {e}");
+            continue;
+        }
+
+        let before = *transform;
+        transform.translation = correction.translation().to_vec3(axis.translation);
+        if let Some(mut s) = smoothing {
+            s.add_correction(&before, &transform);
         }
+        warn!("force replicated translation at tick: {}", correction.tick());
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn handle_correct_rotation<R>(
-    mut query: Query<(&mut Transform, &R), With<Owning>>,
+    mut query: Query<(
+        &mut Transform, 
+        &mut ComponentSnapshots<R>,
+        Option<&mut CorrectionSmoothing>
+    ), 
+        With<Owning>
+    >,
     mut force_replication: EventReader<ForceReplicateRotation<R>>,
-    axis: Res<TransformAxis>
+    axis: Res<TransformAxis>,
+    clock: Res<NetworkClock>
 )
 where R: NetworkRotation {
-    for _ in force_replication.read() {
-        if let Ok((mut transform, net_rotation)) = query.get_single_mut() {
-            transform.rotation = net_rotation.to_quat(axis.rotation);
-            warn!("force replicated rotation");
+    for correction in force_replication.read() {
+        let (mut transform, mut rot_snaps, smoothing) = match query.get_single_mut() {
+            Ok(q) => q,
+            Err(_) => continue
+        };
+
+        let timestamp = match clock.now() {
+            Ok(t) => t,
+            Err(e) => {
+                error!("could not get timestamp: {e}");
+                continue;
+            }
+        };
+
+        // fails if newer state is already replicated
+        if let Err(e) = rot_snaps.insert(
+            correction.rotation().clone(), 
+            correction.tick(), 
+            timestamp
+        ) {
+            warn!("discarding rotation correction: {e}");
+            continue;
+        }
+
+        let before = *transform;
+        transform.rotation = correction.rotation().to_quat(axis.rotation);
+        if let Some(mut s) = smoothing {
+            s.add_correction(&before, &transform);
+        }
+        warn!("force replicated rotation at tick: {}", correction.tick());
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn rollback_system<T, R, E, S>(
+    mut query: Query<(
+        &mut Transform,
+        &T,
+        &R,
+        &ComponentSnapshots<T>,
+        &ComponentSnapshots<R>,
+        &mut EventSnapshots<E>,
+        Option<&mut CorrectionSmoothing>
+    ), 
+        With<Owning>
+    >,
+    axis: Res<TransformAxis>,
+    movement_step: Res<S>
+)
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement,
+S: MovementStep<E> {
+    for (
+        mut transform,
+        net_trans,
+        net_rot,
+        trans_snaps,
+        rot_snaps,
+        mut movements,
+        smoothing
+    ) in query.iter_mut() {
+        // authoritative state is in frontier until it is cached in post update
+        let tick = match (
+            trans_snaps.frontier_back(), 
+            rot_snaps.frontier_back()
+        ) {
+            (Some(t), Some(r)) => t.tick().max(r.tick()),
+            (Some(t), None) => t.tick(),
+            (None, Some(r)) => r.tick(),
+            (None, None) => continue
+        };
+        // movements applied on server are unknown until acknowledged,
+        // prediction is kept as it is
+        let acknowledged_index = match movements.acknowledged_at(tick) {
+            Some(i) => i,
+            None => {
+                debug!("no movement is acknowledged at tick: {tick}, skipping rollback");
+                continue;
+            }
+        };
+
+        // latest snapshot can be newer than replicated component
+        // when correction is folded into history
+        let mut rewound = *transform;
+        rewound.translation = match trans_snaps.frontier_back() {
+            Some(s) => s.component().to_vec3(axis.translation),
+            None => net_trans.to_vec3(axis.translation)
+        };
+        rewound.rotation = match rot_snaps.frontier_back() {
+            Some(s) => s.component().to_quat(axis.rotation),
+            None => net_rot.to_quat(axis.rotation)
+        };
+
+        // cache is applied movements, frontier is applied in next update
+        movements.prune_acknowledged(acknowledged_index);
+        let mut replayed = 0;
+        for snap in movements.cache_ref()
+        .iter() {
+            movement_step.step(&mut rewound, snap.event());
+            replayed += 1;
+        }
+
+        debug!("rolled back to tick: {tick} and replayed {replayed} movements");
+        if let Some(mut s) = smoothing {
+            s.add_correction(&transform, &rewound);
+        }
+        *transform = rewound;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visual_rotation_follows_hermite_strategy() {
+        let mut world = World::new();
+        world.insert_resource(TransformAxis::default());
+        world.insert_resource(InterpolationConfig {
+            network_tick_delta: 0.5,
+            mode: InterpolationMode::Interpolate,
+            timeline: InterpolationTimeline::ReceiveTime,
+            strategy: InterpolationStrategy::Hermite
+        });
+        world.insert_resource(HermiteInterpolation::<NetworkAngle>::new());
+        world.insert_resource(NetworkClock::manual(1.25));
+
+        let mut snaps = ComponentSnapshots::with_init(NetworkAngle(0.0), 0, 0.0, 8)
+        .unwrap();
+        snaps.insert(NetworkAngle(0.0), 1, 0.5).unwrap();
+        snaps.insert(NetworkAngle(90.0), 2, 1.0).unwrap();
+        let e = world.spawn((
+            Transform::IDENTITY,
+            NetworkAngle(90.0),
+            snaps,
+            VisualTransform::new(&Transform::IDENTITY)
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_network_rotation_system::<NetworkAngle>);
+        schedule.run(&mut world);
+
+        // hermite eases out of the stop, linear would be at 45
+        let visual = world.get::<VisualTransform>(e).unwrap().rotation();
+        let expected = Quat::from_rotation_z(39.375f32.to_radians());
+        assert!(visual.angle_between(expected) < 1e-3, "{visual}");
+        let transform = world.get::<Transform>(e).unwrap();
+        let latest = Quat::from_rotation_z(90f32.to_radians());
+        assert!(transform.rotation.angle_between(latest) < 1e-3);
+    }
+
+    #[derive(Resource)]
+    struct Step;
+
+    impl MovementStep<NetworkMovement2D> for Step {
+        fn step(&self, transform: &mut Transform, movement: &NetworkMovement2D) {
+            transform.translation += movement.linear_axis.extend(0.0);
+        }
+    }
+
+    #[test]
+    fn rollback_replays_after_acknowledged_index() {
+        let mut world = World::new();
+        world.insert_resource(TransformAxis::default());
+        world.insert_resource(Step);
+
+        let mut movements = EventSnapshots::with_capacity(8);
+        for index in 0..4 {
+            let timestamp = index as f64 * 0.1;
+            movements.insert(NetworkMovement2D {
+                linear_axis: Vec2::X,
+                index,
+                timestamp,
+                ..default()
+            }, 0, timestamp, timestamp).unwrap();
         }
+        movements.cache();
+
+        let e = world.spawn((
+            Owning,
+            Transform::from_xyz(10.0, 0.0, 0.0),
+            NetworkTranslation2D(Vec2::ZERO),
+            NetworkAngle(0.0),
+            ComponentSnapshots::with_init(NetworkTranslation2D(Vec2::ZERO), 10, 1.0, 8)
+            .unwrap(),
+            ComponentSnapshots::with_init(NetworkAngle(0.0), 10, 1.0, 8)
+            .unwrap(),
+            movements
+        )).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(rollback_system::<
+            NetworkTranslation2D, 
+            NetworkAngle, 
+            NetworkMovement2D, 
+            Step
+        >);
+
+        // not acknowledged, prediction is kept
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Transform>(e).unwrap().translation.x, 10.0);
+
+        world.get_mut::<EventSnapshots<NetworkMovement2D>>(e)
+        .unwrap()
+        .acknowledge(1, 10);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Transform>(e).unwrap().translation.x, 2.0);
+        assert_eq!(world.get::<EventSnapshots<NetworkMovement2D>>(e)
+            .unwrap()
+            .cache_len(), 2
+        );
     }
 }
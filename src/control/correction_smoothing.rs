@@ -0,0 +1,330 @@
+use bevy::{
+    prelude::*,
+    transform::TransformSystem
+};
+use bevy_replicon::prelude::*;
+
+/// radians of rotation error snapped to identity, 
+/// exponential decay never reaches zero by itself
+pub const ROTATION_ERROR_EPSILON: f32 = 1e-4;
+
+#[derive(Clone, Copy)]
+pub enum SmoothingMode {
+    /// error *= exp(-rate * delta)
+    Exponential {
+        rate: f32
+    },
+    /// error reaches zero after duration seconds from latest correction
+    Linear {
+        duration: f32
+    }
+}
+
+impl Default for SmoothingMode {
+    #[inline]
+    fn default() -> Self {
+        Self::Exponential { rate: 10.0 }
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct SmoothingConfig {
+    pub mode: SmoothingMode,
+    /// error larger than this is not smoothed, visual snaps too
+    pub snap_distance: f32
+}
+
+/// visual error left by corrections,
+/// rendered = simulated + error
+#[derive(Component)]
+pub struct CorrectionSmoothing {
+    translation_error: Vec3,
+    rotation_error: Quat,
+    remaining: f32
+}
+
+impl Default for CorrectionSmoothing {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            translation_error: Vec3::ZERO,
+            rotation_error: Quat::IDENTITY,
+            remaining: 0.0
+        }
+    }
+}
+
+impl CorrectionSmoothing {
+    #[inline]
+    pub fn translation_error(&self) -> Vec3 {
+        self.translation_error
+    }
+
+    #[inline]
+    pub fn rotation_error(&self) -> Quat {
+        self.rotation_error
+    }
+
+    #[inline]
+    pub fn is_smoothing(&self) -> bool {
+        self.translation_error != Vec3::ZERO
+        || self.rotation_error != Quat::IDENTITY
+    }
+
+    /// accumulates error of simulated transform moved from before to after
+    #[inline]
+    pub fn add_correction(&mut self, before: &Transform, after: &Transform) {
+        self.translation_error += before.translation - after.translation;
+        self.rotation_error = (
+            self.rotation_error
+            * before.rotation
+            * after.rotation.inverse()
+        ).normalize();
+        self.remaining = 0.0;
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        *self = default();
+    }
+
+    pub fn decay(&mut self, mode: SmoothingMode, delta_seconds: f32) {
+        if !self.is_smoothing() {
+            return;
+        }
+
+        let per = match mode {
+            SmoothingMode::Exponential { rate } => (-rate * delta_seconds).exp(),
+            SmoothingMode::Linear { duration } => {
+                if self.remaining <= 0.0 {
+                    self.remaining = duration;
+                }
+
+                if self.remaining <= delta_seconds {
+                    0.0
+                } else {
+                    let per = 1.0 - delta_seconds / self.remaining;
+                    self.remaining -= delta_seconds;
+                    per
+                }
+            }
+        };
+
+        if per <= 0.0 || self.translation_error.length_squared() < f32::EPSILON {
+            self.translation_error = Vec3::ZERO;
+        } else {
+            self.translation_error *= per;
+        }
+
+        if per <= 0.0 
+        || self.rotation_error.angle_between(Quat::IDENTITY) < ROTATION_ERROR_EPSILON {
+            self.rotation_error = Quat::IDENTITY;
+        } else {
+            self.rotation_error = Quat::IDENTITY.slerp(self.rotation_error, per);
+        }
+    }
+}
+
+/// rendered transform of entity split from simulated one.
+/// interpolation writes here instead of `Transform` when it exists, 
+/// so that colliders stay at the latest authoritative state
+#[derive(Component, Clone, Copy)]
+pub struct VisualTransform {
+    translation: Vec3,
+    rotation: Quat
+}
+
+impl VisualTransform {
+    #[inline]
+    pub fn new(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation
+        }
+    }
+
+    #[inline]
+    pub fn translation(&self) -> Vec3 {
+        self.translation
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    #[inline]
+    pub fn set_translation(&mut self, translation: Vec3) {
+        self.translation = translation;
+    }
+
+    #[inline]
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+    }
+}
+
+/// child entity rendered with visual transform and error of parent
+#[derive(Component)]
+pub struct SmoothedVisual;
+
+fn decay_smoothing_system(
+    mut query: Query<&mut CorrectionSmoothing>,
+    config: Res<SmoothingConfig>,
+    time: Res<Time>
+) {
+    for mut smoothing in query.iter_mut() {
+        if !smoothing.is_smoothing() {
+            continue;
+        }
+
+        if smoothing.translation_error.length() > config.snap_distance {
+            smoothing.clear();
+            continue;
+        }
+
+        smoothing.decay(config.mode, time.delta_seconds());
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_smoothed_visual_system(
+    parents: Query<(
+        &Transform, 
+        Option<&CorrectionSmoothing>, 
+        Option<&VisualTransform>,
+        &Children
+    ), (
+        Or<(
+            Changed<CorrectionSmoothing>, 
+            Changed<VisualTransform>, 
+            Changed<Transform>
+        )>, 
+        Without<SmoothedVisual>
+    )>,
+    mut visuals: Query<&mut Transform, With<SmoothedVisual>>
+) {
+    for (transform, smoothing, visual_transform, children) in parents.iter() {
+        if smoothing.is_none() && visual_transform.is_none() {
+            continue;
+        }
+
+        let (mut translation, mut rotation) = match visual_transform {
+            Some(v) => (v.translation, v.rotation),
+            None => (transform.translation, transform.rotation)
+        };
+        if let Some(s) = smoothing {
+            translation += s.translation_error();
+            rotation = s.rotation_error() * rotation;
+        }
+
+        // rendered is in world space, visual is in parent space
+        let inverse = transform.rotation.inverse();
+        for &child in children.iter() {
+            if let Ok(mut visual) = visuals.get_mut(child) {
+                visual.translation = inverse * (translation - transform.translation);
+                visual.rotation = inverse * rotation;
+            }
+        }
+    }
+}
+
+/// renders `SmoothedVisual` children of `VisualTransform` entities,
+/// added by transform plugins and `CorrectionSmoothingPlugin`
+pub(crate) struct SmoothedVisualPlugin;
+
+impl Plugin for SmoothedVisualPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, 
+            apply_smoothed_visual_system
+            .before(TransformSystem::TransformPropagate)
+        );
+    }
+}
+
+pub struct CorrectionSmoothingPlugin {
+    pub mode: SmoothingMode,
+    pub snap_distance: f32
+}
+
+impl Plugin for CorrectionSmoothingPlugin {
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<RepliconServer>() {
+            // server is not rendered
+        } else if app.world.contains_resource::<RepliconClient>() {
+            app.insert_resource(SmoothingConfig{
+                mode: self.mode,
+                snap_distance: self.snap_distance
+            })
+            .add_systems(PostUpdate, 
+                decay_smoothing_system
+                .before(apply_smoothed_visual_system)
+            );
+
+            if !app.is_plugin_added::<SmoothedVisualPlugin>() {
+                app.add_plugins(SmoothedVisualPlugin);
+            }
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_smoothing_reaches_zero_after_duration() {
+        let mut smoothing = CorrectionSmoothing::default();
+        smoothing.add_correction(
+            &Transform::from_xyz(1.0, 0.0, 0.0),
+            &Transform::IDENTITY
+        );
+        assert_eq!(smoothing.translation_error(), Vec3::X);
+
+        let mode = SmoothingMode::Linear { duration: 0.4 };
+        smoothing.decay(mode, 0.1);
+        assert!((smoothing.translation_error().x - 0.75).abs() < 1e-5);
+        smoothing.decay(mode, 0.1);
+        assert!((smoothing.translation_error().x - 0.5).abs() < 1e-5);
+        smoothing.decay(mode, 0.25);
+        assert!(!smoothing.is_smoothing());
+    }
+
+    #[test]
+    fn exponential_smoothing_snaps_to_zero() {
+        let mut smoothing = CorrectionSmoothing::default();
+        smoothing.add_correction(
+            &Transform::from_xyz(1.0, 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_y(1.0)),
+            &Transform::IDENTITY
+        );
+
+        let mode = SmoothingMode::Exponential { rate: 10.0 };
+        for _ in 0..200 {
+            smoothing.decay(mode, 0.1);
+        }
+        assert_eq!(smoothing.translation_error(), Vec3::ZERO);
+        assert_eq!(smoothing.rotation_error(), Quat::IDENTITY);
+        assert!(!smoothing.is_smoothing());
+    }
+
+    #[test]
+    fn visual_transform_is_rendered_without_smoothing() {
+        let mut app = App::new();
+        app.add_plugins(SmoothedVisualPlugin);
+
+        let visual = app.world.spawn((Transform::IDENTITY, SmoothedVisual))
+        .id();
+        let transform = Transform::from_xyz(1.0, 0.0, 0.0);
+        let mut visual_transform = VisualTransform::new(&transform);
+        visual_transform.set_translation(Vec3::new(2.0, 0.0, 0.0));
+        app.world.spawn((transform, visual_transform))
+        .add_child(visual);
+
+        app.update();
+        let rendered = app.world.get::<Transform>(visual).unwrap();
+        assert_eq!(rendered.translation, Vec3::X);
+    }
+}
@@ -1,6 +1,11 @@
 use serde::{Serialize, Deserialize};
 use bevy::prelude::*;
-use crate::core::{NetworkTranslation, LinearInterpolatable};
+use crate::core::{
+    catmull_rom,
+    NetworkTranslation, 
+    LinearInterpolatable, 
+    HermiteInterpolatable
+};
 
 #[derive(Component, Serialize, Deserialize, Clone, Copy, Default)]
 pub struct NetworkCharacterController(pub Vec3);
@@ -23,3 +28,21 @@ impl LinearInterpolatable for NetworkCharacterController {
         Self(self.0.lerp(rhs.0, per))
     }
 }
+
+impl HermiteInterpolatable for NetworkCharacterController {
+    #[inline]
+    fn hermite_interpolate(
+        prev: &Self, 
+        from: &Self, 
+        to: &Self, 
+        next: &Self, 
+        prev_span: f32,
+        next_span: f32,
+        per: f32
+    ) -> Self {
+        Self(catmull_rom(
+            prev.0, from.0, to.0, next.0, 
+            prev_span, next_span, per
+        ))
+    }
+}
@@ -7,8 +7,26 @@ pub struct NetworkTranslation2D(pub Vec2);
 
 impl LinearInterpolatable for NetworkTranslation2D {
     #[inline]
-    fn linear_interpolate(&self, rhs: &Self, s: f32) -> Self {
-        Self(self.0.lerp(rhs.0, s))
+    fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+        Self(self.0.lerp(rhs.0, per))
+    }
+}
+
+impl HermiteInterpolatable for NetworkTranslation2D {
+    #[inline]
+    fn hermite_interpolate(
+        prev: &Self, 
+        from: &Self, 
+        to: &Self, 
+        next: &Self, 
+        prev_span: f32,
+        next_span: f32,
+        per: f32
+    ) -> Self {
+        Self(catmull_rom(
+            prev.0, from.0, to.0, next.0, 
+            prev_span, next_span, per
+        ))
     }
 }
 
@@ -37,8 +55,26 @@ pub struct NetworkTranslation3D(pub Vec3);
 
 impl LinearInterpolatable for NetworkTranslation3D {
     #[inline]
-    fn linear_interpolate(&self, rhs: &Self, s: f32) -> Self {
-        Self(self.0.lerp(rhs.0, s))
+    fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+        Self(self.0.lerp(rhs.0, per))
+    }
+}
+
+impl HermiteInterpolatable for NetworkTranslation3D {
+    #[inline]
+    fn hermite_interpolate(
+        prev: &Self, 
+        from: &Self, 
+        to: &Self, 
+        next: &Self, 
+        prev_span: f32,
+        next_span: f32,
+        per: f32
+    ) -> Self {
+        Self(catmull_rom(
+            prev.0, from.0, to.0, next.0, 
+            prev_span, next_span, per
+        ))
     }
 }
 
@@ -57,9 +93,10 @@ impl NetworkTranslation for NetworkTranslation3D {
 #[derive(Component, Serialize, Deserialize, Default, Clone, Copy)]
 pub struct NetworkAngle(pub f32);
 
-impl LinearInterpolatable for NetworkAngle {
+impl NetworkAngle {
+    /// degrees of shortest turn from self to rhs, -180 ~ 180
     #[inline]
-    fn linear_interpolate(&self, rhs: &Self, t: f32) -> Self {
+    fn delta(&self, rhs: &Self) -> f32 {
         let mut delta = (rhs.0 - self.0) % 360.0;
         if delta < 0.0 {
             delta += 360.0;
@@ -68,9 +105,36 @@ impl LinearInterpolatable for NetworkAngle {
         if delta > 180.0 {
             delta -= 360.0;
         }
+        delta
+    }
+}
+
+impl LinearInterpolatable for NetworkAngle {
+    #[inline]
+    fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+        Self((self.0 + self.delta(rhs) * per) % 360.0)
+    }
+}
 
-        delta *= t;
-        Self((self.0 + delta) % 360.0)
+impl HermiteInterpolatable for NetworkAngle {
+    #[inline]
+    fn hermite_interpolate(
+        prev: &Self, 
+        from: &Self, 
+        to: &Self, 
+        next: &Self, 
+        prev_span: f32,
+        next_span: f32,
+        per: f32
+    ) -> Self {
+        // unwrap around from so that curve takes shortest turns
+        let unwrapped_prev = from.0 - prev.delta(from);
+        let unwrapped_to = from.0 + from.delta(to);
+        let unwrapped_next = unwrapped_to + to.delta(next);
+        Self(catmull_rom(
+            unwrapped_prev, from.0, unwrapped_to, unwrapped_next, 
+            prev_span, next_span, per
+        ) % 360.0)
     }
 }
 
@@ -94,4 +158,130 @@ impl NetworkRotation for NetworkAngle {
     }
 }
 
+/// full 3d rotation, 
+/// serialized into 32 bits with smallest three compression
+#[derive(Component, Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(from = "u32", into = "u32")]
+pub struct NetworkQuat(pub Quat);
+
+/// 2 bits for index of largest component and 10 bits for each of the rest
+const QUAT_COMPONENT_BITS: u32 = 10;
+const QUAT_COMPONENT_MAX: f32 = ((1 << QUAT_COMPONENT_BITS) - 1) as f32;
 
+impl NetworkQuat {
+    pub fn to_bits(&self) -> u32 {
+        let q = self.0.normalize().to_array();
+        let mut largest = 0;
+        for i in 1..4 {
+            if q[i].abs() > q[largest].abs() {
+                largest = i;
+            }
+        }
+
+        // q and -q are same rotation, keep largest positive to drop its sign
+        let sign = if q[largest] < 0.0 { -1.0 } else { 1.0 };
+        let mut bits = largest as u32;
+        for (i, v) in q.iter().enumerate() {
+            if i == largest {
+                continue;
+            }
+
+            // rest of components are in -1/sqrt(2) ~ 1/sqrt(2)
+            let per = (v * sign * std::f32::consts::SQRT_2 * 0.5 + 0.5)
+            .clamp(0.0, 1.0);
+            bits = (bits << QUAT_COMPONENT_BITS) 
+            | (per * QUAT_COMPONENT_MAX).round() as u32;
+        }
+        bits
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        let mask = (1 << QUAT_COMPONENT_BITS) - 1;
+        let largest = (bits >> (QUAT_COMPONENT_BITS * 3)) as usize & 0b11;
+        
+        let mut q = [0.0; 4];
+        let mut sum = 0.0;
+        let mut shift = QUAT_COMPONENT_BITS * 3;
+        for (i, v) in q.iter_mut().enumerate() {
+            if i == largest {
+                continue;
+            }
+
+            shift -= QUAT_COMPONENT_BITS;
+            let per = ((bits >> shift) & mask) as f32 / QUAT_COMPONENT_MAX;
+            *v = (per * 2.0 - 1.0) / std::f32::consts::SQRT_2;
+            sum += *v * *v;
+        }
+        q[largest] = (1.0 - sum).max(0.0).sqrt();
+
+        Self(Quat::from_array(q).normalize())
+    }
+}
+
+impl From<u32> for NetworkQuat {
+    #[inline]
+    fn from(bits: u32) -> Self {
+        Self::from_bits(bits)
+    }
+}
+
+impl From<NetworkQuat> for u32 {
+    #[inline]
+    fn from(quat: NetworkQuat) -> Self {
+        quat.to_bits()
+    }
+}
+
+impl LinearInterpolatable for NetworkQuat {
+    #[inline]
+    fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+        Self(self.0.slerp(rhs.0, per))
+    }
+}
+
+impl NetworkRotation for NetworkQuat {
+    #[inline]
+    fn from_quat(quat: Quat, _: RotationAxis) -> Self {
+        Self(quat)
+    }
+
+    #[inline]
+    fn to_quat(&self, _: RotationAxis) -> Quat {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_angle_hermite_takes_shortest_turn() {
+        let angles = [
+            NetworkAngle(340.0), 
+            NetworkAngle(350.0), 
+            NetworkAngle(0.0), 
+            NetworkAngle(10.0)
+        ];
+        let mid = NetworkAngle::hermite_interpolate(
+            &angles[0], &angles[1], &angles[2], &angles[3], 
+            1.0, 1.0, 0.5
+        );
+        assert!((mid.0 - 355.0).abs() < 1e-3, "{}", mid.0);
+    }
+
+    #[test]
+    fn network_quat_survives_compression() {
+        let rotations = [
+            Quat::IDENTITY,
+            Quat::from_rotation_y(-2.5),
+            Quat::from_euler(EulerRot::YXZ, 1.0, -0.5, 2.0),
+            -Quat::from_euler(EulerRot::XYZ, 0.3, 3.0, -1.2)
+        ];
+
+        for r in rotations {
+            let decoded = NetworkQuat::from_bits(NetworkQuat(r).to_bits());
+            assert!(decoded.0.angle_between(r) < 0.01, "{r} -> {}", decoded.0);
+        }
+    }
+}
@@ -5,6 +5,11 @@ pub mod interpolation;
 pub mod prediction;
 pub mod boot_system_set;
 pub mod player_start_line;
+pub mod network_clock;
+pub mod clock_sync;
+pub mod max_rewind;
+pub mod teleport;
+pub mod network_parent;
 
 pub use network_entity::*;
 pub use network_event::*;
@@ -13,6 +18,11 @@ pub use interpolation::*;
 pub use prediction::*;
 pub use boot_system_set::*;
 pub use player_start_line::*;
+pub use network_clock::*;
+pub use clock_sync::*;
+pub use max_rewind::*;
+pub use teleport::*;
+pub use network_parent::*;
 
 use serde::{Serialize, de::DeserializeOwned};
 use bevy::prelude::*;
@@ -2,10 +2,12 @@ use bevy::prelude::*;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum ServerBootSet {
+    ClockSync,
     UnboxEvent,
     PlayerEntityEvent,
     CorrectReplication,
     Update,
+    LagCompensation,
     Culling,
     Grouping,
     ApplyLocalChange,
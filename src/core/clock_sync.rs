@@ -0,0 +1,399 @@
+use std::collections::VecDeque;
+use serde::{Serialize, Deserialize};
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use bevy_replicon::prelude::*;
+use super::{
+    network_clock::NetworkClock,
+    interpolation::{InterpolationConfig, RenderDelay},
+    boot_system_set::{ClientBootSet, ServerBootSet}
+};
+
+/// sent by server, carries server timestamp on sending
+#[derive(Event, Serialize, Deserialize, Clone)]
+pub struct ClockSyncPing {
+    pub server_timestamp: f64
+}
+
+/// sent back by client as soon as ping is received,
+/// with seconds remote entities are rendered behind on client
+#[derive(Event, Serialize, Deserialize, Clone)]
+pub struct ClockSyncPong {
+    pub server_timestamp: f64,
+    pub client_timestamp: f64,
+    pub render_delay: Option<f64>
+}
+
+/// sent back to client with current estimate for the client
+#[derive(Event, Serialize, Deserialize, Clone)]
+pub struct ClockSyncReport {
+    pub offset: f64,
+    pub rtt: f64
+}
+
+#[derive(Clone, Copy)]
+pub struct ClockSample {
+    pub offset: f64,
+    pub rtt: f64
+}
+
+/// offset and rtt estimate for a client.
+/// offset is client clock - server clock
+pub struct ClockSync {
+    samples: VecDeque<ClockSample>,
+    sample_size: usize,
+    offset: f64,
+    rtt: f64,
+    render_delay: Option<f64>
+}
+
+impl ClockSync {
+    #[inline]
+    pub fn with_capacity(sample_size: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(sample_size),
+            sample_size,
+            offset: 0.0,
+            rtt: 0.0,
+            render_delay: None
+        }
+    }
+
+    #[inline]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// latest render delay reported by client
+    #[inline]
+    pub fn render_delay(&self) -> Option<f64> {
+        self.render_delay
+    }
+
+    #[inline]
+    pub fn rtt(&self) -> f64 {
+        self.rtt
+    }
+
+    #[inline]
+    pub fn sample_len(&self) -> usize {
+        self.samples.len()
+    }
+
+    #[inline]
+    pub fn to_server_time(&self, client_timestamp: f64) -> f64 {
+        client_timestamp - self.offset
+    }
+
+    /// keeps latest samples and takes the one with smallest rtt,
+    /// that is least affected by asymmetric delay
+    pub fn add_sample(&mut self, sample: ClockSample) {
+        if self.sample_size == 0 {
+            return;
+        }
+
+        if self.samples.len() >= self.sample_size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        // samples is not empty
+        let best = self.samples.iter()
+        .min_by(|l, r| l.rtt.total_cmp(&r.rtt))
+        .unwrap();
+        self.offset = best.offset;
+        self.rtt = best.rtt;
+    }
+}
+
+#[derive(Resource)]
+pub struct ClockSyncMap {
+    map: HashMap<ClientId, ClockSync>,
+    sample_size: usize
+}
+
+impl ClockSyncMap {
+    #[inline]
+    pub fn with_capacity(sample_size: usize) -> Self {
+        Self {
+            map: default(),
+            sample_size
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, client_id: &ClientId) -> Option<&ClockSync> {
+        self.map.get(client_id)
+    }
+
+    #[inline]
+    pub fn add_sample(&mut self, client_id: ClientId, sample: ClockSample) {
+        let sample_size = self.sample_size;
+        self.map.entry(client_id)
+        .or_insert_with(|| ClockSync::with_capacity(sample_size))
+        .add_sample(sample);
+    }
+
+    /// ignored until the client is sampled
+    #[inline]
+    pub fn set_render_delay(&mut self, client_id: &ClientId, render_delay: f64) {
+        if let Some(s) = self.map.get_mut(client_id) {
+            s.render_delay = Some(render_delay);
+        }
+    }
+
+    #[inline]
+    pub fn remove(&mut self, client_id: &ClientId) {
+        self.map.remove(client_id);
+    }
+
+    /// client timestamp in server clock,
+    /// None if client is not sampled yet
+    #[inline]
+    pub fn to_server_time(&self, client_id: &ClientId, client_timestamp: f64) -> Option<f64> {
+        self.map.get(client_id)
+        .filter(|s| s.sample_len() > 0)
+        .map(|s| s.to_server_time(client_timestamp))
+    }
+}
+
+/// client side view of server clock, reported by server
+#[derive(Resource, Default)]
+pub struct ServerClock {
+    offset: f64,
+    rtt: f64,
+    is_synced: bool
+}
+
+impl ServerClock {
+    #[inline]
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    #[inline]
+    pub fn rtt(&self) -> f64 {
+        self.rtt
+    }
+
+    #[inline]
+    pub fn is_synced(&self) -> bool {
+        self.is_synced
+    }
+
+    #[inline]
+    pub fn to_server_time(&self, local_timestamp: f64) -> f64 {
+        local_timestamp - self.offset
+    }
+
+    #[inline]
+    pub fn to_local_time(&self, server_timestamp: f64) -> f64 {
+        server_timestamp + self.offset
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct ClockSyncConfig {
+    pub ping_interval: f64
+}
+
+fn send_ping_system(
+    mut pings: EventWriter<ToClients<ClockSyncPing>>,
+    mut last_ping: Local<Option<f64>>,
+    config: Res<ClockSyncConfig>,
+    clock: Res<NetworkClock>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+
+    if let Some(last) = *last_ping {
+        if now - last < config.ping_interval {
+            return;
+        }
+    }
+
+    pings.send(ToClients {
+        mode: SendMode::Broadcast,
+        event: ClockSyncPing { server_timestamp: now }
+    });
+    *last_ping = Some(now);
+}
+
+fn handle_pong_system(
+    mut pongs: EventReader<FromClient<ClockSyncPong>>,
+    mut reports: EventWriter<ToClients<ClockSyncReport>>,
+    mut clock_sync: ResMut<ClockSyncMap>,
+    clock: Res<NetworkClock>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+
+    for FromClient { client_id, event } in pongs.read() {
+        if !event.server_timestamp.is_finite()
+        || !event.client_timestamp.is_finite() {
+            warn!("discarding: malformatted pong from: {client_id:?}");
+            continue;
+        }
+
+        let rtt = now - event.server_timestamp;
+        if rtt < 0.0 {
+            warn!("discarding: pong from: {client_id:?} is future");
+            continue;
+        }
+
+        let offset = event.client_timestamp - (event.server_timestamp + now) * 0.5;
+        clock_sync.add_sample(*client_id, ClockSample { offset, rtt });
+        match event.render_delay {
+            Some(d) if d.is_finite() && d >= 0.0 => clock_sync.set_render_delay(client_id, d),
+            Some(d) => warn!("discarding: render delay: {d} from: {client_id:?}"),
+            None => ()
+        }
+        debug!("clock sync: {client_id:?} offset: {offset} rtt: {rtt}");
+
+        if let Some(s) = clock_sync.get(client_id) {
+            reports.send(ToClients {
+                mode: SendMode::Direct(*client_id),
+                event: ClockSyncReport {
+                    offset: s.offset(),
+                    rtt: s.rtt()
+                }
+            });
+        }
+    }
+}
+
+fn handle_disconnected_system(
+    mut server_events: EventReader<ServerEvent>,
+    mut clock_sync: ResMut<ClockSyncMap>
+) {
+    for e in server_events.read() {
+        if let ServerEvent::ClientDisconnected { client_id, reason: _ } = e {
+            clock_sync.remove(client_id);
+        }
+    }
+}
+
+fn handle_report_system(
+    mut reports: EventReader<ClockSyncReport>,
+    mut server_clock: ResMut<ServerClock>
+) {
+    for report in reports.read() {
+        if !report.offset.is_finite() || !report.rtt.is_finite() {
+            warn!("discarding: malformatted clock sync report");
+            continue;
+        }
+
+        server_clock.offset = report.offset;
+        server_clock.rtt = report.rtt;
+        server_clock.is_synced = true;
+    }
+}
+
+fn handle_ping_system(
+    mut pings: EventReader<ClockSyncPing>,
+    mut pongs: EventWriter<ClockSyncPong>,
+    render_delay: Option<Res<RenderDelay>>,
+    interpolation_config: Option<Res<InterpolationConfig>>,
+    clock: Res<NetworkClock>
+) {
+    let render_delay = match (render_delay, interpolation_config) {
+        (Some(d), Some(c)) => Some(d.delay(c.network_tick_delta)),
+        _ => None
+    };
+
+    for ping in pings.read() {
+        let client_timestamp = match clock.now() {
+            Ok(t) => t,
+            Err(e) => {
+                error!("could not get timestamp: {e}");
+                return;
+            }
+        };
+
+        pongs.send(ClockSyncPong {
+            server_timestamp: ping.server_timestamp,
+            client_timestamp,
+            render_delay
+        });
+    }
+}
+
+pub struct ClockSyncPlugin {
+    pub ping_interval: f64,
+    pub sample_size: usize
+}
+
+impl Plugin for ClockSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkClock>()
+        .add_server_event::<ClockSyncPing>(ChannelKind::Unreliable)
+        .add_client_event::<ClockSyncPong>(ChannelKind::Unreliable)
+        .add_server_event::<ClockSyncReport>(ChannelKind::Unreliable);
+
+        if app.world.contains_resource::<RepliconServer>() {
+            app.insert_resource(ClockSyncConfig{
+                ping_interval: self.ping_interval
+            })
+            .insert_resource(ClockSyncMap::with_capacity(self.sample_size))
+            .add_systems(PreUpdate, (
+                handle_disconnected_system,
+                handle_pong_system
+            ).chain(
+            ).in_set(ServerBootSet::ClockSync))
+            .add_systems(PostUpdate,
+                send_ping_system
+                .before(ServerSet::Send)
+            );
+        } else if app.world.contains_resource::<RepliconClient>() {
+            app.init_resource::<ServerClock>()
+            .add_systems(PreUpdate, (
+                handle_ping_system,
+                handle_report_system
+            ).after(ClientSet::Receive
+            ).before(ClientBootSet::UnboxReplication));
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_sync_takes_smallest_rtt_sample() {
+        let mut sync = ClockSync::with_capacity(2);
+        sync.add_sample(ClockSample { offset: 1.0, rtt: 0.2 });
+        sync.add_sample(ClockSample { offset: 0.5, rtt: 0.1 });
+        assert_eq!((sync.offset(), sync.rtt()), (0.5, 0.1));
+        assert_eq!(sync.to_server_time(10.5), 10.0);
+
+        sync.add_sample(ClockSample { offset: 2.0, rtt: 0.3 });
+        sync.add_sample(ClockSample { offset: 3.0, rtt: 0.4 });
+        assert_eq!(sync.sample_len(), 2);
+        assert_eq!((sync.offset(), sync.rtt()), (2.0, 0.3));
+    }
+
+    #[test]
+    fn unsampled_client_has_no_server_time() {
+        let mut map = ClockSyncMap::with_capacity(2);
+        let client_id = ClientId::new(1);
+        assert_eq!(map.to_server_time(&client_id, 10.5), None);
+
+        map.add_sample(client_id, ClockSample { offset: 0.5, rtt: 0.1 });
+        assert_eq!(map.to_server_time(&client_id, 10.5), Some(10.0));
+    }
+}
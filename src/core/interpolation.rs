@@ -1,10 +1,445 @@
+use std::{
+    collections::VecDeque,
+    ops::{Add, Mul, Sub}
+};
 use bevy::prelude::*;
+use bevy_replicon::client::confirm_history::ConfirmHistory;
+use super::network_clock::NetworkClock;
+
+/// number of received ticks to estimate server tick from
+pub const TICK_ESTIMATE_SAMPLE_SIZE: usize = 32;
+
+/// adaptive render timeline runs at most this much faster or slower
+pub const RENDER_DELAY_ADAPT_RATE: f64 = 0.05;
+
+/// standard deviations of arrival jitter covered by adaptive delay
+pub const RENDER_DELAY_JITTER_SCALE: f64 = 2.0;
+
+/// longer gap between received ticks is taken as ticks nothing changed on, 
+/// changes are re-sent every tick until acknowledged so loss leaves short gaps
+pub const MAX_LOSS_GAP_TICKS: u32 = 4;
+
+#[derive(Clone, Copy, Default)]
+pub enum InterpolationMode {
+    /// stays at the latest snapshot once a network tick elapsed
+    #[default]
+    Interpolate,
+    /// keeps moving along the last two snapshots
+    /// up to max time once a network tick elapsed
+    Extrapolate {
+        max_time: f64
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum InterpolationTimeline {
+    /// latest two snapshots by local receive timestamp
+    #[default]
+    ReceiveTime,
+    /// snapshots around estimated server tick - delay ticks
+    ServerTick {
+        delay_ticks: u32
+    },
+    /// same as server tick, but delay follows measured jitter 
+    /// and packet loss within min and max
+    AdaptiveServerTick {
+        min_delay_ticks: u32,
+        max_delay_ticks: u32
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum InterpolationStrategy {
+    #[default]
+    Linear,
+    /// cubic hermite over neighbor snapshots, 
+    /// applied to components registered by `hermite` of their plugins,
+    /// others stay linear
+    Hermite
+}
 
 #[derive(Resource, Clone)]
 pub struct InterpolationConfig {
-    pub network_tick_delta: f64
+    pub network_tick_delta: f64,
+    pub mode: InterpolationMode,
+    pub timeline: InterpolationTimeline,
+    pub strategy: InterpolationStrategy
+}
+
+impl InterpolationConfig {
+    /// how long remote entities are rendered behind the latest received.
+    /// adaptive delay is only known by client, this is the lower bound
+    #[inline]
+    pub fn render_delay(&self) -> f64 {
+        match self.timeline {
+            InterpolationTimeline::ReceiveTime => self.network_tick_delta,
+            InterpolationTimeline::ServerTick { delay_ticks } 
+            | InterpolationTimeline::AdaptiveServerTick { 
+                min_delay_ticks: delay_ticks, 
+                max_delay_ticks: _ 
+            } => delay_ticks as f64 * self.network_tick_delta
+        }
+    }
 }
 
 pub trait LinearInterpolatable: Component + Clone {
     fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self;
 }
+
+/// C1 continuous curve through samples, 
+/// per is 0.0 ~ 1.0 between from and to.
+/// prev span and next span are ticks of prev -> from and to -> next
+/// relative to ticks of from -> to
+pub trait HermiteInterpolatable: LinearInterpolatable {
+    fn hermite_interpolate(
+        prev: &Self, 
+        from: &Self, 
+        to: &Self, 
+        next: &Self, 
+        prev_span: f32,
+        next_span: f32,
+        per: f32
+    ) -> Self;
+}
+
+/// cubic hermite with catmull-rom tangents,
+/// each tangent is scaled by its span so that unevenly spaced samples 
+/// keep their velocity
+#[inline]
+pub fn catmull_rom<V>(
+    prev: V, 
+    from: V, 
+    to: V, 
+    next: V, 
+    prev_span: f32,
+    next_span: f32,
+    per: f32
+) -> V
+where V: Copy + Add<Output = V> + Sub<Output = V> + Mul<f32, Output = V> {
+    let from_tangent = (to - prev) * (1.0 / (prev_span.max(0.0) + 1.0));
+    let to_tangent = (next - from) * (1.0 / (next_span.max(0.0) + 1.0));
+    let per2 = per * per;
+    let per3 = per2 * per;
+
+    from * (2.0 * per3 - 3.0 * per2 + 1.0)
+    + from_tangent * (per3 - 2.0 * per2 + per)
+    + to * (-2.0 * per3 + 3.0 * per2)
+    + to_tangent * (per3 - per2)
+}
+
+/// estimates latest server tick arriving on client from received ticks.
+/// keeps the earliest arrival in the window, that is least affected by jitter
+#[derive(Resource)]
+pub struct ServerTickEstimate {
+    /// received tick and local timestamp tick 0 would have arrived
+    samples: VecDeque<(u32, f64)>,
+    /// received ticks of any entity, ordered
+    received: VecDeque<u32>,
+    sample_size: usize,
+    offset: Option<f64>,
+    latest_tick: u32
+}
+
+impl Default for ServerTickEstimate {
+    #[inline]
+    fn default() -> Self {
+        Self::with_capacity(TICK_ESTIMATE_SAMPLE_SIZE)
+    }
+}
+
+impl ServerTickEstimate {
+    #[inline]
+    pub fn with_capacity(sample_size: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(sample_size),
+            received: VecDeque::with_capacity(sample_size),
+            sample_size,
+            offset: None,
+            latest_tick: 0
+        }
+    }
+
+    #[inline]
+    pub fn latest_tick(&self) -> u32 {
+        self.latest_tick
+    }
+
+    /// tick received at local timestamp
+    pub fn add_sample(&mut self, tick: u32, timestamp: f64, network_tick_delta: f64) {
+        if self.sample_size == 0 
+        || (tick <= self.latest_tick && self.offset.is_some()) {
+            return;
+        }
+
+        if self.samples.len() >= self.sample_size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((tick, timestamp - tick as f64 * network_tick_delta));
+        self.latest_tick = tick;
+
+        self.offset = self.samples.iter()
+        .map(|(_, o)| *o)
+        .min_by(|l, r| l.total_cmp(r));
+    }
+
+    /// standard deviation of arrival in seconds
+    pub fn jitter(&self) -> f64 {
+        let len = self.samples.len();
+        if len < 2 {
+            return 0.0;
+        }
+
+        let mean = self.samples.iter()
+        .map(|(_, o)| *o)
+        .sum::<f64>() / len as f64;
+        let variance = self.samples.iter()
+        .map(|(_, o)| (*o - mean).powi(2))
+        .sum::<f64>() / len as f64;
+        variance.sqrt()
+    }
+
+    /// tick confirmed by replication, 
+    /// duplicated one or older than the window is ignored
+    pub fn add_received_tick(&mut self, tick: u32) {
+        if self.sample_size == 0 {
+            return;
+        }
+
+        let idx = match self.received.binary_search(&tick) {
+            Ok(_) => return,
+            Err(i) => i
+        };
+        if idx == 0 && self.received.len() >= self.sample_size {
+            return;
+        }
+
+        self.received.insert(idx, tick);
+        if self.received.len() > self.sample_size {
+            self.received.pop_front();
+        }
+    }
+
+    /// ratio of ticks not received in the window, 0.0 ~ 1.0.
+    /// only short gaps are counted, see [`MAX_LOSS_GAP_TICKS`]
+    pub fn packet_loss(&self) -> f32 {
+        let lost = self.received.iter()
+        .zip(self.received.iter().skip(1))
+        .map(|(l, r)| r - l - 1)
+        .filter(|gap| *gap <= MAX_LOSS_GAP_TICKS)
+        .sum::<u32>();
+        if lost == 0 {
+            return 0.0;
+        }
+
+        lost as f32 / (lost as usize + self.received.len()) as f32
+    }
+
+    /// fractional server tick arriving at now, None before first sample
+    #[inline]
+    pub fn estimate(&self, now: f64, network_tick_delta: f64) -> Option<f64> {
+        self.offset.map(|o| (now - o) / network_tick_delta)
+    }
+
+    /// fractional server tick remote entities are rendered at
+    #[inline]
+    pub fn render_tick(&self, now: f64, network_tick_delta: f64, delay_ticks: f64) 
+    -> Option<f64> {
+        self.estimate(now, network_tick_delta)
+        .map(|t| t - delay_ticks)
+    }
+}
+
+/// current render delay of remote entities on client,
+/// with measurements it is adapted from
+#[derive(Resource, Clone, Default)]
+pub struct RenderDelay {
+    delay_ticks: f64,
+    jitter: f64,
+    packet_loss: f32
+}
+
+impl RenderDelay {
+    #[inline]
+    pub fn from_config(config: &InterpolationConfig) -> Self {
+        Self {
+            delay_ticks: config.render_delay() / config.network_tick_delta,
+            ..default()
+        }
+    }
+
+    #[inline]
+    pub fn delay_ticks(&self) -> f64 {
+        self.delay_ticks
+    }
+
+    #[inline]
+    pub fn delay(&self, network_tick_delta: f64) -> f64 {
+        self.delay_ticks * network_tick_delta
+    }
+
+    #[inline]
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    #[inline]
+    pub fn packet_loss(&self) -> f32 {
+        self.packet_loss
+    }
+
+    /// one tick to have a pair of snapshots around, 
+    /// plus jitter and expected ticks to wait for lost one
+    #[inline]
+    pub fn target_ticks(&self, network_tick_delta: f64) -> f64 {
+        let loss = (self.packet_loss as f64).min(0.9);
+        1.0 
+        + RENDER_DELAY_JITTER_SCALE * self.jitter / network_tick_delta
+        + loss / (1.0 - loss)
+    }
+}
+
+/// fractional server tick remote entities are rendered at,
+/// None if timeline is receive time or tick is not estimated yet
+#[inline]
+pub fn render_tick(
+    config: &InterpolationConfig,
+    estimate: Option<&ServerTickEstimate>,
+    render_delay: Option<&RenderDelay>,
+    now: f64
+) -> Option<f64> {
+    match (config.timeline, estimate, render_delay) {
+        (InterpolationTimeline::ReceiveTime, _, _) => None,
+        (_, Some(e), Some(d)) => e.render_tick(
+            now, 
+            config.network_tick_delta, 
+            d.delay_ticks()
+        ),
+        _ => None
+    }
+}
+
+pub(crate) fn estimate_server_tick_system(
+    query: Query<&ConfirmHistory, Changed<ConfirmHistory>>,
+    mut estimate: ResMut<ServerTickEstimate>,
+    config: Res<InterpolationConfig>,
+    clock: Res<NetworkClock>
+) {
+    let mut latest = None;
+    for history in query.iter() {
+        let last_tick = history.last_tick();
+        // mask covers 64 ticks, window is no longer than sample size
+        let len = (estimate.sample_size as u32).min(u64::BITS);
+        for ago in 0..len.min(last_tick.get() + 1) {
+            let tick = last_tick - ago;
+            if history.contains(tick) {
+                estimate.add_received_tick(tick.get());
+            }
+        }
+        latest = latest.max(Some(last_tick.get()));
+    }
+
+    let tick = match latest {
+        Some(t) => t,
+        None => return
+    };
+
+    match clock.now() {
+        Ok(now) => estimate.add_sample(tick, now, config.network_tick_delta),
+        Err(e) => error!("could not get timestamp: {e}")
+    }
+}
+
+pub(crate) fn adapt_render_delay_system(
+    mut render_delay: ResMut<RenderDelay>,
+    estimate: Res<ServerTickEstimate>,
+    config: Res<InterpolationConfig>,
+    time: Res<Time>
+) {
+    render_delay.jitter = estimate.jitter();
+    render_delay.packet_loss = estimate.packet_loss();
+
+    match config.timeline {
+        InterpolationTimeline::ReceiveTime 
+        | InterpolationTimeline::ServerTick { delay_ticks: _ } => {
+            render_delay.delay_ticks = config.render_delay() / config.network_tick_delta;
+        }
+        InterpolationTimeline::AdaptiveServerTick { min_delay_ticks, max_delay_ticks } => {
+            let target = render_delay.target_ticks(config.network_tick_delta)
+            .clamp(min_delay_ticks as f64, max_delay_ticks as f64);
+            // change slowly so that remote entities do not warp
+            let max_step = RENDER_DELAY_ADAPT_RATE 
+            * time.delta_seconds_f64() 
+            / config.network_tick_delta;
+            let step = (target - render_delay.delay_ticks)
+            .clamp(-max_step, max_step);
+            render_delay.delay_ticks += step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catmull_rom_passes_through_samples() {
+        let (prev, from, to, next) = (0.0, 1.0, 3.0, 4.0);
+        assert_eq!(catmull_rom(prev, from, to, next, 1.0, 1.0, 0.0), from);
+        assert_eq!(catmull_rom(prev, from, to, next, 1.0, 1.0, 1.0), to);
+
+        // straight line stays linear
+        let mid = catmull_rom(
+            Vec2::ZERO, Vec2::X, Vec2::X * 2.0, Vec2::X * 3.0, 
+            1.0, 1.0, 0.5
+        );
+        assert!((mid - Vec2::X * 1.5).length() < 1e-6);
+
+        // constant velocity over ticks 0, 2, 3, 5 stays linear
+        let mid = catmull_rom(0.0, 2.0, 3.0, 5.0, 2.0, 2.0, 0.5);
+        assert!((mid - 2.5f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn estimate_takes_earliest_arrival() {
+        let mut estimate = ServerTickEstimate::with_capacity(4);
+        assert!(estimate.estimate(0.0, 0.1).is_none());
+
+        estimate.add_sample(10, 1.05, 0.1);
+        // arrived late by jitter
+        estimate.add_sample(11, 1.25, 0.1);
+        assert!((estimate.estimate(1.25, 0.1).unwrap() - 12.0).abs() < 1e-6);
+
+        // older tick is ignored
+        estimate.add_sample(9, 0.0, 0.1);
+        assert!((estimate.estimate(1.25, 0.1).unwrap() - 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn jitter_and_loss_raise_target_delay() {
+        let mut estimate = ServerTickEstimate::with_capacity(8);
+        for tick in 0..4 {
+            estimate.add_sample(tick, tick as f64 * 0.1, 0.1);
+        }
+        assert!(estimate.jitter() < 1e-6);
+        assert_eq!(estimate.packet_loss(), 0.0);
+
+        // tick 4 is lost, tick 5 is late
+        estimate.add_sample(5, 0.55, 0.1);
+        assert!(estimate.jitter() > 0.0);
+        for tick in [5, 0, 1, 2, 3, 3] {
+            estimate.add_received_tick(tick);
+        }
+        assert!((estimate.packet_loss() - 1.0 / 6.0).abs() < 1e-6);
+
+        // replication skips ticks nothing changed on
+        estimate.add_received_tick(20);
+        assert!((estimate.packet_loss() - 1.0 / 7.0).abs() < 1e-6);
+
+        let render_delay = RenderDelay {
+            delay_ticks: 1.0,
+            jitter: estimate.jitter(),
+            packet_loss: estimate.packet_loss()
+        };
+        assert!(render_delay.target_ticks(0.1) > 1.2);
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+#[derive(Clone, Copy)]
+pub enum RewindWindow {
+    Duration {
+        seconds: f64
+    },
+    /// converted into duration by network tick delta
+    Ticks {
+        ticks: u32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RewindExceeded {
+    /// lookup is done at the bound of window exceeded
+    Clamp,
+    /// lookup is skipped
+    Reject
+}
+
+/// limits how far back history lookups driven by client timestamps can reach
+#[derive(Resource, Clone)]
+pub struct MaxRewind {
+    pub window: RewindWindow,
+    pub on_exceeded: RewindExceeded
+}
+
+impl MaxRewind {
+    #[inline]
+    pub fn window_seconds(&self, network_tick_delta: f64) -> f64 {
+        match self.window {
+            RewindWindow::Duration { seconds } => seconds,
+            RewindWindow::Ticks { ticks } => ticks as f64 * network_tick_delta
+        }
+    }
+
+    #[inline]
+    pub fn oldest_timestamp(&self, now: f64, network_tick_delta: f64) -> f64 {
+        now - self.window_seconds(network_tick_delta)
+    }
+
+    /// bound of window timestamp exceeded, None if within window.
+    /// window is oldest timestamp ~ now, future is not rendered by anyone
+    #[inline]
+    pub fn exceeded_bound(&self, timestamp: f64, now: f64, network_tick_delta: f64) 
+    -> Option<f64> {
+        let oldest_timestamp = self.oldest_timestamp(now, network_tick_delta);
+        if timestamp < oldest_timestamp {
+            Some(oldest_timestamp)
+        } else if timestamp > now {
+            Some(now)
+        } else {
+            None
+        }
+    }
+
+    /// timestamp to look up, None if rejected.
+    /// sends rejection if timestamp is out of window
+    pub fn apply(
+        &self,
+        client_id: ClientId,
+        timestamp: f64,
+        now: f64,
+        network_tick_delta: f64,
+        rejections: &mut EventWriter<RewindRejected>
+    ) -> Option<f64> {
+        let bound = match self.exceeded_bound(timestamp, now, network_tick_delta) {
+            Some(b) => b,
+            None => return Some(timestamp)
+        };
+
+        rejections.send(RewindRejected {
+            client_id,
+            timestamp,
+            bound,
+            action: self.on_exceeded
+        });
+
+        match self.on_exceeded {
+            RewindExceeded::Clamp => Some(bound),
+            RewindExceeded::Reject => None
+        }
+    }
+}
+
+/// sent on server for each lookup with client timestamp 
+/// older than max rewind or later than now
+#[derive(Event, Clone, Debug)]
+pub struct RewindRejected {
+    pub client_id: ClientId,
+    pub timestamp: f64,
+    /// oldest timestamp or now
+    pub bound: f64,
+    pub action: RewindExceeded
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+    use super::*;
+
+    fn apply(max_rewind: &MaxRewind, timestamps: &[f64], now: f64) 
+    -> (Vec<Option<f64>>, Vec<f64>) {
+        let mut world = World::new();
+        world.init_resource::<Events<RewindRejected>>();
+        let mut state = SystemState::<EventWriter<RewindRejected>>::new(&mut world);
+        let mut rejections = state.get_mut(&mut world);
+        let applied = timestamps.iter()
+        .map(|&t| max_rewind.apply(ClientId::new(1), t, now, 0.1, &mut rejections))
+        .collect();
+
+        let bounds = world.resource_mut::<Events<RewindRejected>>()
+        .drain()
+        .map(|r| r.bound)
+        .collect();
+        (applied, bounds)
+    }
+
+    #[test]
+    fn clamp_limits_into_window() {
+        let max_rewind = MaxRewind {
+            window: RewindWindow::Ticks { ticks: 5 },
+            on_exceeded: RewindExceeded::Clamp
+        };
+
+        let (applied, bounds) = apply(&max_rewind, &[9.8, 9.0, 10.5], 10.0);
+        assert_eq!(applied, vec![Some(9.8), Some(9.5), Some(10.0)]);
+        assert_eq!(bounds, vec![9.5, 10.0]);
+    }
+
+    #[test]
+    fn reject_skips_out_of_window() {
+        let max_rewind = MaxRewind {
+            window: RewindWindow::Duration { seconds: 0.5 },
+            on_exceeded: RewindExceeded::Reject
+        };
+
+        let (applied, bounds) = apply(&max_rewind, &[10.0, 9.0, 10.5], 10.0);
+        assert_eq!(applied, vec![Some(10.0), None, None]);
+        assert_eq!(bounds, vec![9.5, 10.0]);
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::{
+    prelude::*,
+    utils::{Instant, SystemTime}
+};
+
+/// clock used for every timestamp stamped on snapshots
+/// and every elapsed time calculated on interpolation
+#[derive(Resource, Clone)]
+pub enum NetworkClock {
+    /// unix time from system clock, can jump by ntp adjustments
+    RealTime,
+    /// unix time at origin plus monotonic elapsed time
+    Monotonic {
+        origin: Instant,
+        origin_timestamp: f64
+    },
+    /// stays until advanced, useful for tests
+    Manual {
+        timestamp: f64
+    }
+}
+
+impl Default for NetworkClock {
+    #[inline]
+    fn default() -> Self {
+        Self::RealTime
+    }
+}
+
+impl NetworkClock {
+    #[inline]
+    pub fn monotonic() -> anyhow::Result<Self> {
+        let origin_timestamp = Self::RealTime.now()?;
+        Ok(Self::Monotonic {
+            origin: Instant::now(),
+            origin_timestamp
+        })
+    }
+
+    #[inline]
+    pub fn manual(timestamp: f64) -> Self {
+        Self::Manual { timestamp }
+    }
+
+    #[inline]
+    pub fn now(&self) -> anyhow::Result<f64> {
+        match self {
+            Self::RealTime => Ok(
+                SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs_f64()
+            ),
+            Self::Monotonic { origin, origin_timestamp } => Ok(
+                origin_timestamp + origin.elapsed().as_secs_f64()
+            ),
+            Self::Manual { timestamp } => Ok(*timestamp)
+        }
+    }
+
+    /// advances manual clock, does nothing on other clocks
+    #[inline]
+    pub fn advance(&mut self, seconds: f64) {
+        if let Self::Manual { timestamp } = self {
+            *timestamp += seconds;
+        }
+    }
+
+    /// sets manual clock, does nothing on other clocks
+    #[inline]
+    pub fn set(&mut self, seconds: f64) {
+        if let Self::Manual { timestamp } = self {
+            *timestamp = seconds;
+        }
+    }
+}
@@ -17,8 +17,114 @@ impl NetworkEntity {
     }
 }
 
-#[derive(Component)]
-pub struct PlayerView;
+/// field of view culling uses, facing is taken from
+/// rotation of the view unless the client sent its camera direction recently
+#[derive(Clone, Copy)]
+pub struct ViewCone {
+    /// cosine of half of field of view
+    half_fov_cos: f32,
+    /// local direction the view looks along
+    forward: Vec3,
+    /// world direction sent by client and when it was received
+    facing: Option<(Vec3, f64)>
+}
+
+impl ViewCone {
+    /// fov is full angle in radians
+    #[inline]
+    pub fn new(fov: f32, forward: Vec3) -> Self {
+        Self {
+            half_fov_cos: (fov * 0.5).cos(),
+            forward: forward.normalize_or_zero(),
+            facing: None
+        }
+    }
+
+    /// None clears override, rotation is used again
+    #[inline]
+    pub fn set_facing(&mut self, facing: Option<Vec3>, timestamp: f64) {
+        self.facing = facing.map(|f| (f.normalize_or_zero(), timestamp));
+    }
+
+    /// sent direction is used until timeout seconds elapsed
+    #[inline]
+    pub fn facing(&self, rotation: Quat, now: f64, timeout: f64) -> Vec3 {
+        match self.facing {
+            Some((f, t)) if now - t <= timeout => f,
+            _ => rotation * self.forward
+        }
+    }
+
+    /// to_target is from view to target
+    #[inline]
+    pub fn contains(&self, facing: Vec3, to_target: Vec3) -> bool {
+        let dir = to_target.normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return true;
+        }
+
+        facing.dot(dir) >= self.half_fov_cos
+    }
+}
+
+#[derive(Component, Default)]
+pub struct PlayerView {
+    cone: Option<ViewCone>
+}
+
+impl PlayerView {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_cone(cone: ViewCone) -> Self {
+        Self { 
+            cone: Some(cone) 
+        }
+    }
+
+    #[inline]
+    pub fn cone(&self) -> Option<&ViewCone> {
+        self.cone.as_ref()
+    }
+
+    #[inline]
+    pub fn cone_mut(&mut self) -> Option<&mut ViewCone> {
+        self.cone.as_mut()
+    }
+}
 
 #[derive(Component)]
 pub struct Owning;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_cone_follows_rotation_unless_facing_is_sent() {
+        let mut cone = ViewCone::new(90f32.to_radians(), Vec3::NEG_Z);
+        let facing = cone.facing(Quat::IDENTITY, 0.0, 1.0);
+        assert!(cone.contains(facing, Vec3::new(0.5, 0.0, -1.0)));
+        assert!(!cone.contains(facing, Vec3::new(0.0, 0.0, 1.0)));
+
+        let facing = cone.facing(Quat::from_rotation_y(180f32.to_radians()), 0.0, 1.0);
+        assert!(cone.contains(facing, Vec3::new(0.0, 0.0, 1.0)));
+
+        cone.set_facing(Some(Vec3::X * 2.0), 0.0);
+        let facing = cone.facing(Quat::IDENTITY, 0.5, 1.0);
+        assert!(cone.contains(facing, Vec3::X));
+        assert!(!cone.contains(facing, Vec3::NEG_Z));
+
+        // timed out
+        let facing = cone.facing(Quat::IDENTITY, 1.5, 1.0);
+        assert!(cone.contains(facing, Vec3::NEG_Z));
+
+        cone.set_facing(Some(Vec3::X), 2.0);
+        cone.set_facing(None, 2.0);
+        let facing = cone.facing(Quat::IDENTITY, 2.0, 1.0);
+        assert!(cone.contains(facing, Vec3::NEG_Z));
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Serialize, Deserialize};
+use bevy::{
+    ecs::entity::{EntityMapper, MapEntities},
+    prelude::*
+};
+use bevy_replicon::prelude::*;
+use super::{
+    network_clock::NetworkClock,
+    teleport::TeleportCounter
+};
+
+/// max depth of hierarchy walked up to find root
+pub const MAX_HIERARCHY_DEPTH: usize = 8;
+
+/// replicated parent of entity, network transform is relative to it.
+/// follows `Parent` on server, applied as `Parent` on client
+#[derive(Component, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NetworkParent(Entity);
+
+impl NetworkParent {
+    #[inline]
+    pub fn new(parent: Entity) -> Self {
+        Self(parent)
+    }
+
+    #[inline]
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl MapEntities for NetworkParent {
+    #[inline]
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
+
+/// root of hierarchy, entity itself if it has no parent.
+/// None if hierarchy is deeper than max depth
+pub fn network_root(entity: Entity, parents: &Query<&NetworkParent>) -> Option<Entity> {
+    let mut current = entity;
+    // one more lookup to find current has no parent
+    for _ in 0..=MAX_HIERARCHY_DEPTH {
+        match parents.get(current) {
+            Ok(p) => current = p.get(),
+            Err(_) => return Some(current)
+        }
+    }
+
+    None
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn store_network_parent_system(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity, 
+        &Parent, 
+        Option<&NetworkParent>, 
+        Option<&mut TeleportCounter>
+    ), (
+        Changed<Parent>, 
+        With<Replicated>
+    )>,
+    replicated: Query<(), With<Replicated>>,
+    clock: Res<NetworkClock>
+) {
+    for (entity, parent, network_parent, counter) in query.iter_mut() {
+        let parent = parent.get();
+        // parent is not on client, transform stays world space there
+        if !replicated.contains(parent) {
+            warn!("parent: {parent:?} of: {entity:?} is not replicated");
+            continue;
+        }
+
+        if network_parent.is_some_and(|p| p.get() == parent) {
+            continue;
+        }
+
+        commands.entity(entity)
+        .insert(NetworkParent::new(parent));
+
+        // transform is now relative to another space,
+        // clients must not interpolate across it
+        if let Some(mut c) = counter {
+            match clock.now() {
+                Ok(t) => c.increment(t),
+                Err(e) => error!("could not get timestamp: {e}")
+            }
+        }
+    }
+}
+
+pub(crate) fn store_network_parent_removal_system(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Parent>,
+    mut query: Query<Option<&mut TeleportCounter>, With<NetworkParent>>,
+    clock: Res<NetworkClock>
+) {
+    for entity in removed.read() {
+        let counter = match query.get_mut(entity) {
+            Ok(c) => c,
+            Err(_) => continue
+        };
+
+        commands.entity(entity)
+        .remove::<NetworkParent>();
+        if let Some(mut c) = counter {
+            match clock.now() {
+                Ok(t) => c.increment(t),
+                Err(e) => error!("could not get timestamp: {e}")
+            }
+        }
+    }
+}
+
+pub(crate) fn sync_network_parent_system(
+    mut commands: Commands,
+    query: Query<(Entity, &NetworkParent, Option<&Parent>), Changed<NetworkParent>>,
+    mut removed: RemovedComponents<NetworkParent>,
+    parents: Query<&Parent>
+) {
+    for (entity, network_parent, parent) in query.iter() {
+        if parent.is_some_and(|p| p.get() == network_parent.get()) {
+            continue;
+        }
+
+        commands.entity(entity)
+        .set_parent(network_parent.get());
+    }
+
+    for entity in removed.read() {
+        if parents.contains(entity) {
+            commands.entity(entity)
+            .remove_parent();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+    use super::*;
+
+    #[test]
+    fn network_root_is_found_within_max_depth() {
+        let mut world = World::new();
+        let root = world.spawn_empty().id();
+        let mut chain = vec![root];
+        for _ in 0..=MAX_HIERARCHY_DEPTH {
+            let parent = *chain.last().unwrap();
+            chain.push(world.spawn(NetworkParent::new(parent)).id());
+        }
+
+        let mut state = SystemState::<Query<&NetworkParent>>::new(&mut world);
+        let parents = state.get(&world);
+        assert_eq!(network_root(root, &parents), Some(root));
+        assert_eq!(network_root(chain[MAX_HIERARCHY_DEPTH], &parents), Some(root));
+        assert_eq!(network_root(chain[MAX_HIERARCHY_DEPTH + 1], &parents), None);
+    }
+}
@@ -35,14 +35,66 @@ impl<C: Component + Serialize + DeserializeOwned> PredioctionError<C> {
     }
 }
 
-#[derive(Event, Serialize, Deserialize, Default)]
-pub struct ForceReplicateTranslation<T>(PhantomData<T>)
-where T: NetworkTranslation;
+/// user supplied movement step,
+/// re-applies a movement on top of rewound transform.
+/// must move as far as live handling of the movement does in a tick,
+/// otherwise replayed transform drifts from prediction
+pub trait MovementStep<E: NetworkMovement>: Resource {
+    fn step(&self, transform: &mut Transform, movement: &E);
+}
+
+/// authoritative translation and server tick it belongs to
+#[derive(Event, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ForceReplicateTranslation<T>
+where T: NetworkTranslation {
+    translation: T,
+    tick: u32
+}
+
+impl<T: NetworkTranslation> ForceReplicateTranslation<T> {
+    #[inline]
+    pub fn new(translation: T, tick: u32) -> Self {
+        Self { translation, tick }
+    }
+
+    #[inline]
+    pub fn translation(&self) -> &T {
+        &self.translation
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+}
 
 pub type CorrectTranslation<T> = ToClients<ForceReplicateTranslation<T>>;
 
-#[derive(Event, Serialize, Deserialize, Default)]
-pub struct ForceReplicateRotation<R>(PhantomData<R>)
-where R: NetworkRotation;
+/// authoritative rotation and server tick it belongs to
+#[derive(Event, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ForceReplicateRotation<R>
+where R: NetworkRotation {
+    rotation: R,
+    tick: u32
+}
+
+impl<R: NetworkRotation> ForceReplicateRotation<R> {
+    #[inline]
+    pub fn new(rotation: R, tick: u32) -> Self {
+        Self { rotation, tick }
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> &R {
+        &self.rotation
+    }
+
+    #[inline]
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+}
 
 pub type CorrectRotation<R> = ToClients<ForceReplicateRotation<R>>;
@@ -0,0 +1,74 @@
+use std::marker::PhantomData;
+use serde::{Serialize, Deserialize};
+use bevy::{
+    ecs::entity::{EntityMapper, MapEntities},
+    prelude::*
+};
+use super::network_event::NetworkEvent;
+
+/// replicated count of teleports, clients place the entity directly
+/// without interpolation when it changes
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct TeleportCounter {
+    count: u32,
+    /// server timestamp of the latest teleport
+    timestamp: f64
+}
+
+impl TeleportCounter {
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    #[inline]
+    pub fn timestamp(&self) -> f64 {
+        self.timestamp
+    }
+
+    /// called on server after moving the entity
+    #[inline]
+    pub fn increment(&mut self, timestamp: f64) {
+        self.count = self.count.wrapping_add(1);
+        self.timestamp = timestamp;
+    }
+}
+
+/// sent by server game logic to move an entity instantly,
+/// rotation is kept if None
+#[derive(Event, Clone)]
+pub struct Teleport {
+    pub entity: Entity,
+    pub translation: Vec3,
+    pub rotation: Option<Quat>
+}
+
+/// sent by server to owner client on teleport.
+/// events up to index were received before it and are discarded
+#[derive(Event, Serialize, Deserialize)]
+pub struct Teleported<E: NetworkEvent> {
+    /// mapped to client entity
+    pub entity: Entity,
+    pub index: Option<usize>,
+    pub count: u32,
+    phantom: PhantomData<E>
+}
+
+impl<E: NetworkEvent> Teleported<E> {
+    #[inline]
+    pub fn new(entity: Entity, index: Option<usize>, count: u32) -> Self {
+        Self {
+            entity,
+            index,
+            count,
+            phantom: PhantomData::<E>
+        }
+    }
+}
+
+impl<E: NetworkEvent> MapEntities for Teleported<E> {
+    #[inline]
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.entity = entity_mapper.map_entity(self.entity);
+    }
+}
@@ -1,6 +1,15 @@
 pub mod ee_map;
+pub mod spatial_grid;
 pub mod distance_culling;
 pub mod relevant_group;
+pub mod parent_visibility;
+pub mod network_lod;
+pub mod occlusion_culling;
+pub mod view_cone_culling;
 
+pub use spatial_grid::*;
 pub use distance_culling::*;
 pub use relevant_group::*;
+pub use network_lod::*;
+pub use occlusion_culling::*;
+pub use view_cone_culling::*;
@@ -1,9 +1,12 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
 use bevy_replicon::{
-    prelude::*, 
+    prelude::*,
     server::server_tick::ServerTick
 };
-use super::ee_map::*;
+use super::spatial_grid::*;
 use crate::core::*;
 
 #[derive(Component)]
@@ -29,68 +32,194 @@ impl Default for Culling {
     }
 }
 
+impl Culling {
+    /// distance is squared distance
+    #[inline]
+    pub fn is_visible(&self, distance: f32, threshold: f32) -> bool {
+        match *self {
+            Culling::Default => distance < threshold,
+            Culling::Modify { addition, multiplier } => {
+                addition + distance * multiplier < threshold
+            }
+            Culling::Disable => true
+        }
+    }
+
+    /// radius entity can be visible within, infinite if it is not bounded
+    pub fn radius(&self, threshold: f32) -> f32 {
+        match *self {
+            Culling::Default => threshold.max(0.0).sqrt(),
+            Culling::Modify { addition, multiplier } => {
+                if multiplier <= 0.0 {
+                    return f32::INFINITY;
+                }
+
+                ((threshold - addition) / multiplier).max(0.0).sqrt()
+            }
+            Culling::Disable => f32::INFINITY
+        }
+    }
+}
+
+/// thresholds are compared with squared distance,
+/// entity becomes visible within enter and is culled out of exit
+#[derive(Resource)]
+pub struct CullingConfig {
+    pub enter_threshold: f32,
+    pub exit_threshold: f32,
+    /// seconds entity stays visible at least once it entered
+    pub min_visible_duration: f64,
+    pub clean_up_on_disconnect: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct VisibleState {
+    /// tick and timestamp it became visible
+    pub tick: u32,
+    pub timestamp: f64
+}
+
 #[derive(Default, Clone, Copy)]
 pub struct DistanceAt {
     pub tick: u32,
     pub distance: f32
 }
 
-pub type DistanceMap = EntityPairMap<DistanceAt>;
+/// squared distance from player view to entities visible to it,
+/// updated on every culling
+#[derive(Resource, Default)]
+pub struct DistanceMap(HashMap<(Entity, Entity), DistanceAt>);
 
-#[derive(Resource)]
-pub struct CullingConfig {
-    pub culling_threshold: f32,
-    pub clean_up_on_disconnect: bool,
+impl DistanceMap {
+    #[inline]
+    pub fn insert(&mut self, player: Entity, entity: Entity, v: DistanceAt) 
+    -> Option<DistanceAt> {
+        self.0.insert((player, entity), v)
+    }
+
+    #[inline]
+    pub fn get(&self, player: Entity, entity: Entity) -> Option<&DistanceAt> {
+        self.0.get(&(player, entity))
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, player: Entity, entity: Entity) -> Option<&mut DistanceAt> {
+        self.0.get_mut(&(player, entity))
+    }
+
+    /// ((player, entity), distance)
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&(Entity, Entity), &DistanceAt)> {
+        self.0.iter()
+    }
+
+    #[inline]
+    pub fn remove_pair(&mut self, player: Entity, entity: Entity) {
+        self.0.remove(&(player, entity));
+    }
+
+    /// removes entity both as player and as target
+    #[inline]
+    pub fn remove(&mut self, entity: Entity) {
+        self.0.retain(|k, _| k.0 != entity && k.1 != entity);
+    }
 }
 
-fn calculate_distance_system(
-    query: Query<(Entity, &Transform), (Changed<Transform>, With<Culling>)>,
-    player_views: Query<(Entity, &Transform), With<PlayerView>>,
-    mut distance_map: ResMut<DistanceMap>,
-    server_tick: Res<ServerTick>
+/// entities made visible by distance culling for each player view
+#[derive(Resource, Default)]
+pub struct CulledVisibleMap(HashMap<Entity, HashMap<Entity, VisibleState>>);
+
+impl CulledVisibleMap {
+    #[inline]
+    pub fn get(&self, player: Entity) -> Option<&HashMap<Entity, VisibleState>> {
+        self.0.get(&player)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, player: Entity) -> Option<&mut HashMap<Entity, VisibleState>> {
+        self.0.get_mut(&player)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&Entity, &HashMap<Entity, VisibleState>)> {
+        self.0.iter()
+    }
+
+    #[inline]
+    pub fn remove(&mut self, entity: Entity) {
+        self.0.remove(&entity);
+        for visible in self.0.values_mut() {
+            visible.remove(&entity);
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_grid_system(
+    query: Query<
+        (Entity, &Transform, &Culling),
+        (Or<(Changed<Transform>, Changed<Culling>)>, Without<NetworkParent>)
+    >,
+    roots: Query<(&Transform, &Culling), Without<NetworkParent>>,
+    children: Query<Entity, (With<Culling>, Added<NetworkParent>)>,
+    mut removed: RemovedComponents<Culling>,
+    mut unparented: RemovedComponents<NetworkParent>,
+    mut grid: ResMut<SpatialGrid>,
+    culling_config: Res<CullingConfig>
 ) {
-    for (player_e, player_t) in player_views.iter() {    
-        let tick = server_tick.get();
-        for (e, t) in query.iter() {
-            if player_e == e {
-                continue;
-            }
+    for entity in removed.read() {
+        grid.remove(entity);
+    }
 
-            if let Some(d) = distance_map.get(player_e, e) {
-                if d.tick == tick {
-                    continue;
-                }
-            }
+    // children follow visibility of their root
+    for entity in children.iter() {
+        grid.remove(entity);
+    }
 
-            let distance = player_t.translation.distance_squared(t.translation);
-            let distance_at = DistanceAt{
-                tick,
-                distance
-            };
-            
-            distance_map.insert(player_e, e, distance_at);
-            debug!(
-                "updated distance from: {:?} to: {:?} tick: {} distance: {}",
-                player_e, e,
-                tick, 
-                distance
-            );
-        }        
+    // exit is the farthest entity can stay visible
+    let exit = culling_config.exit_threshold;
+
+    // detached one may not move, so it is not caught by change
+    for e in unparented.read() {
+        if let Ok((t, culling)) = roots.get(e) {
+            grid.insert(e, t.translation, culling.radius(exit));
+            debug!("detached entity: {e:?} is back in grid");
+        }
+    }
+
+    for (e, t, culling) in query.iter() {
+        grid.insert(e, t.translation, culling.radius(exit));
+        debug!("updated grid: {:?} cell: {}", e, grid.cell(t.translation));
     }
+
+    grid.refresh_max_radius();
 }
 
-fn culling_system(
-    query: Query<(Entity, &Culling)>,
-    player_views: Query<(Entity, &NetworkEntity), With<PlayerView>>,
-    distance_map: Res<DistanceMap>,
+#[allow(clippy::too_many_arguments)]
+pub(super) fn culling_system(
+    query: Query<(&Transform, &Culling), Without<NetworkParent>>,
+    player_views: Query<(Entity, &NetworkEntity, &Transform), With<PlayerView>>,
+    grid: Res<SpatialGrid>,
     culling_config: Res<CullingConfig>,
-    mut connected_clients: ResMut<ConnectedClients>
+    mut visible_map: ResMut<CulledVisibleMap>,
+    mut distance_map: ResMut<DistanceMap>,
+    mut connected_clients: ResMut<ConnectedClients>,
+    server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>,
+    mut candidates: Local<Vec<Entity>>,
+    mut in_range: Local<HashMap<Entity, f32>>
 ) {
-    if !distance_map.is_changed() {
-        return;
-    }
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let tick = server_tick.get();
+    let max_radius = grid.max_radius();
 
-    for (player_e, player_net_e) in player_views.iter() {
+    for (player_e, player_net_e, player_t) in player_views.iter() {
         let client_id = player_net_e.client_id();
         let visibility = match connected_clients.get_client_mut(client_id) {
             Some(c) => c.visibility_mut(),
@@ -99,56 +228,81 @@ fn culling_system(
                 continue;
             }
         };
-        
-        for (e, culling) in query.iter() {
+
+        candidates.clear();
+        in_range.clear();
+        grid.query(player_t.translation, max_radius, &mut candidates);
+        candidates.extend(grid.oversized());
+
+        let visible = visible_map.0.entry(player_e)
+        .or_default();
+        for &e in candidates.iter() {
             if player_e == e {
                 continue;
             }
 
-            let (addition, multiplier) = match culling {
-                &Culling::Default => (0.0, 0.0),
-                &Culling::Modify { addition, multiplier } => (addition, multiplier),
-                &Culling::Disable => {
-                    if !visibility.is_visible(e) {
-                        visibility.set_visibility(e, true);
-                    }
-                    continue;    
-                }
-                
+            let (t, culling) = match query.get(e) {
+                Ok(q) => q,
+                Err(_) => continue
             };
 
-            let distance = match distance_map.get(player_e, e) {
-                Some(d) => d.distance,
-                None => 0.0
+            let distance = player_t.translation.distance_squared(t.translation);
+            // already visible one stays until it leaves exit threshold
+            let threshold = if visible.contains_key(&e) {
+                culling_config.exit_threshold
+            } else {
+                culling_config.enter_threshold
             };
+            if culling.is_visible(distance, threshold) {
+                in_range.insert(e, distance);
+            }
+        }
 
-            debug!(
-                "checking {player_e:?}:{e:?} distance: {} addition: {} multiplier: {}", 
-                distance,
-                addition,
-                multiplier
-            );
+        for (&e, &distance) in in_range.iter() {
+            if !visibility.is_visible(e) {
+                visibility.set_visibility(e, true);
+            }
+            visible.entry(e)
+            .or_insert(VisibleState { 
+                tick, 
+                timestamp: now
+            });
+            distance_map.insert(player_e, e, DistanceAt { tick, distance });
+        }
 
-            let result = addition + distance * multiplier;
-            if result >= culling_config.culling_threshold {
-                if visibility.is_visible(e) {
-                    visibility.set_visibility(e, false);
-                }
-            } else {
-                if !visibility.is_visible(e) {
-                    visibility.set_visibility(e, true);
+        visible.retain(|&e, since| {
+            if in_range.contains_key(&e) {
+                return true;
+            }
+
+            if now - since.timestamp < culling_config.min_visible_duration {
+                if let Ok((t, _)) = query.get(e) {
+                    let distance = player_t.translation.distance_squared(t.translation);
+                    distance_map.insert(player_e, e, DistanceAt { tick, distance });
+                    return true;
                 }
             }
-        }
+
+            debug!("culled {player_e:?}:{e:?}");
+            distance_map.remove_pair(player_e, e);
+            if visibility.is_visible(e) {
+                visibility.set_visibility(e, false);
+            }
+            false
+        });
     }
 }
 
 fn handle_player_entity_event(
     mut events: EventReader<PlayerEntityEvent>,
+    mut grid: ResMut<SpatialGrid>,
+    mut visible_map: ResMut<CulledVisibleMap>,
     mut distance_map: ResMut<DistanceMap>
 ) {
     for e in events.read() {
         if let &PlayerEntityEvent::Despawned { client_id: _, entity } = e {
+            grid.remove(entity);
+            visible_map.remove(entity);
             distance_map.remove(entity);
         }
     }
@@ -156,32 +310,129 @@ fn handle_player_entity_event(
 
 #[derive(Default)]
 pub struct DistanceCullingPlugin {
-    pub culling_threshold: f32,
+    /// squared distance entity becomes visible within
+    pub enter_threshold: f32,
+    /// squared distance entity is culled out of, 
+    /// larger than enter so that it does not flicker on the edge,
+    /// swapped with enter if smaller
+    pub exit_threshold: f32,
+    /// seconds entity stays visible at least once it entered
+    pub min_visible_duration: f64,
+    /// size of spatial grid cell,
+    /// around culling radius is a good start
+    pub cell_size: f32,
     pub auto_clean: bool
 }
 
 impl Plugin for DistanceCullingPlugin {
     fn build(&self, app: &mut App) {
+        if self.cell_size <= 0.0 {
+            panic!("cell size must be positive: {}", self.cell_size);
+        }
+        let (enter_threshold, exit_threshold) = if self.exit_threshold < self.enter_threshold {
+            warn!(
+                "exit threshold: {} is smaller than enter threshold: {}, swapping them",
+                self.exit_threshold,
+                self.enter_threshold
+            );
+            (self.exit_threshold, self.enter_threshold)
+        } else {
+            (self.enter_threshold, self.exit_threshold)
+        };
+
         if app.world.contains_resource::<RepliconServer>() {
-            app.insert_resource(DistanceMap::default())
+            app.insert_resource(SpatialGrid::new(self.cell_size))
+            .insert_resource(CulledVisibleMap::default())
+            .insert_resource(DistanceMap::default())
             .insert_resource(CullingConfig{
-                culling_threshold: self.culling_threshold,
+                enter_threshold,
+                exit_threshold,
+                min_visible_duration: self.min_visible_duration,
                 clean_up_on_disconnect: self.auto_clean
             })
             .add_systems(PostUpdate, (
-                calculate_distance_system,
+                update_grid_system,
                 culling_system
             ).chain(
             ).in_set(ServerBootSet::Culling));
 
             if self.auto_clean {
-                app.add_systems(PreUpdate, 
+                app.add_systems(PreUpdate,
                     handle_player_entity_event
                     .after(ServerBootSet::PlayerEntityEvent)
                 );
             }
         } else {
             panic!("could not find replicon server");
-        }     
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn culling_app(enter_threshold: f32, exit_threshold: f32) -> (App, ClientId) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(ServerPlugin {
+                visibility_policy: VisibilityPolicy::Whitelist,
+                ..default()
+            })
+        ))
+        .insert_resource(NetworkClock::manual(0.0))
+        .add_plugins(DistanceCullingPlugin {
+            enter_threshold,
+            exit_threshold,
+            min_visible_duration: 1.0,
+            cell_size: 10.0,
+            auto_clean: false
+        });
+
+        let client_id = ClientId::new(1);
+        app.world.resource_mut::<RepliconServer>()
+        .set_running(true);
+        app.world.send_event(ServerEvent::ClientConnected { client_id });
+        app.update();
+        (app, client_id)
+    }
+
+    #[test]
+    fn swapped_thresholds_are_corrected() {
+        let (app, _) = culling_app(400.0, 100.0);
+        let config = app.world.resource::<CullingConfig>();
+        assert_eq!((config.enter_threshold, config.exit_threshold), (100.0, 400.0));
+    }
+
+    #[test]
+    fn visible_entity_stays_within_exit_and_min_duration() {
+        let (mut app, client_id) = culling_app(100.0, 400.0);
+        app.world.spawn((
+            NetworkEntity::new(client_id), 
+            PlayerView::new(), 
+            Transform::IDENTITY
+        ));
+        let e = app.world.spawn((Transform::from_xyz(15.0, 0.0, 0.0), Culling::Default))
+        .id();
+
+        let step = |app: &mut App, x: f32, elapsed: f64| {
+            app.world.get_mut::<Transform>(e).unwrap().translation.x = x;
+            app.world.resource_mut::<NetworkClock>().advance(elapsed);
+            app.update();
+            app.world.resource::<ConnectedClients>()
+            .client(client_id)
+            .visibility()
+            .is_visible(e)
+        };
+
+        // out of enter
+        assert!(!step(&mut app, 15.0, 0.1));
+        assert!(step(&mut app, 5.0, 0.1));
+        // within exit
+        assert!(step(&mut app, 15.0, 0.1));
+        // out of exit, but entered shorter than min duration ago
+        assert!(step(&mut app, 25.0, 0.1));
+        assert!(!step(&mut app, 25.0, 1.0));
     }
 }
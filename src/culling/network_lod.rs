@@ -0,0 +1,201 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use bevy_replicon::prelude::*;
+use super::distance_culling::*;
+use crate::core::*;
+
+#[derive(Clone, Copy)]
+pub struct LodLevel {
+    /// squared distance this level applies within
+    pub threshold: f32,
+    /// network transform is replicated every interval ticks
+    pub interval: u32
+}
+
+/// replication frequency of network transform by distance.
+/// replication is shared by clients, 
+/// so the level is decided by the closest player view.
+/// server history is still recorded every tick, only replication is throttled.
+/// interval is capped to minimum render delay of `InterpolationConfig`,
+/// otherwise clients run out of snapshots and extrapolate between updates
+#[derive(Component, Clone)]
+pub struct NetworkLod {
+    levels: Vec<LodLevel>,
+    interval: u32
+}
+
+impl NetworkLod {
+    /// beyond the farthest level, its interval is used
+    #[inline]
+    pub fn new(mut levels: Vec<LodLevel>) -> Self {
+        levels.sort_by(|l, r| l.threshold.total_cmp(&r.threshold));
+        Self {
+            levels,
+            interval: 1
+        }
+    }
+
+    #[inline]
+    pub fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    pub fn interval_at(&self, distance: f32) -> u32 {
+        let level = self.levels.iter()
+        .find(|l| distance < l.threshold)
+        .or(self.levels.last());
+        match level {
+            Some(l) => l.interval.max(1),
+            None => 1
+        }
+    }
+
+    /// ticks are spread by entity so that far ones do not burst together
+    #[inline]
+    pub fn is_due(&self, entity: Entity, tick: u32) -> bool {
+        self.interval <= 1
+        || tick.wrapping_add(entity.index()).is_multiple_of(self.interval)
+    }
+}
+
+/// render delay in ticks, at least 1
+#[inline]
+pub fn max_interval(config: &InterpolationConfig) -> u32 {
+    let ticks = config.render_delay() / config.network_tick_delta;
+    if ticks.is_finite() {
+        (ticks.floor() as u32).max(1)
+    } else {
+        1
+    }
+}
+
+pub(super) fn update_lod_system(
+    mut query: Query<(Entity, &mut NetworkLod)>,
+    distance_map: Res<DistanceMap>,
+    config: Res<InterpolationConfig>,
+    mut closest: Local<HashMap<Entity, f32>>
+) {
+    let max_interval = max_interval(&config);
+    closest.clear();
+    for (&(_, e), d) in distance_map.iter() {
+        closest.entry(e)
+        .and_modify(|c| *c = c.min(d.distance))
+        .or_insert(d.distance);
+    }
+
+    for (e, mut lod) in query.iter_mut() {
+        // not visible to anyone, nothing is sent anyway
+        let interval = match closest.get(&e) {
+            Some(&d) => lod.interval_at(d).min(max_interval),
+            None => continue
+        };
+
+        if lod.interval != interval {
+            debug!("lod interval of: {e:?} changed to: {interval}");
+            lod.interval = interval;
+        }
+    }
+}
+
+/// requires `DistanceCullingPlugin`
+pub struct NetworkLodPlugin;
+
+impl Plugin for NetworkLodPlugin {
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<RepliconServer>() {
+            app.add_systems(PostUpdate,
+                update_lod_system
+                .after(ServerBootSet::Culling)
+                .before(ServerBootSet::ApplyLocalChange)
+            );
+        } else {
+            panic!("could not find replicon server");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_replicon::server::server_tick::ServerTick;
+    use crate::{
+        control::{apply_transform_translation_system, NetworkTranslation3D},
+        snapshot::ComponentSnapshots
+    };
+    use super::*;
+
+    #[test]
+    fn interval_follows_distance_levels() {
+        let lod = NetworkLod::new(vec![
+            LodLevel { threshold: 400.0, interval: 4 },
+            LodLevel { threshold: 100.0, interval: 1 }
+        ]);
+        assert_eq!(lod.interval_at(50.0), 1);
+        assert_eq!(lod.interval_at(200.0), 4);
+        assert_eq!(lod.interval_at(1000.0), 4);
+        assert_eq!(NetworkLod::new(vec![]).interval_at(1000.0), 1);
+    }
+
+    #[test]
+    fn far_entity_is_replicated_on_due_ticks_only() {
+        let mut world = World::new();
+        world.insert_resource(TransformAxis::default());
+        world.insert_resource(ServerTick::default());
+        world.insert_resource(NetworkClock::manual(0.0));
+        world.insert_resource(InterpolationConfig {
+            network_tick_delta: 0.1,
+            mode: default(),
+            timeline: InterpolationTimeline::ServerTick { delay_ticks: 4 },
+            strategy: default()
+        });
+
+        let player = world.spawn_empty().id();
+        let far = world.spawn((
+            Transform::default(),
+            NetworkTranslation3D::default(),
+            ComponentSnapshots::with_init(NetworkTranslation3D::default(), 0, 0.0, 16)
+            .unwrap(),
+            NetworkLod::new(vec![
+                LodLevel { threshold: 100.0, interval: 1 },
+                // capped to render delay
+                LodLevel { threshold: 10000.0, interval: 8 }
+            ])
+        )).id();
+        let mut distance_map = DistanceMap::default();
+        distance_map.insert(player, far, DistanceAt { tick: 0, distance: 2500.0 });
+        world.insert_resource(distance_map);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            update_lod_system,
+            apply_transform_translation_system::<NetworkTranslation3D>
+        ).chain());
+        // advances change tick past spawn
+        schedule.run(&mut world);
+
+        let mut expected = 0.0;
+        let mut recorded = 1;
+        for tick in 1..=12 {
+            world.resource_mut::<ServerTick>().increment();
+            world.resource_mut::<NetworkClock>().advance(0.1);
+            world.get_mut::<Transform>(far).unwrap().translation.x = tick as f32;
+            schedule.run(&mut world);
+
+            let lod = world.get::<NetworkLod>(far).unwrap();
+            assert_eq!(lod.interval(), 4);
+            if lod.is_due(far, tick) {
+                expected = tick as f32;
+            } else {
+                // snapshot of due tick is recorded on change of component
+                recorded += 1;
+            }
+
+            let replicated = world.get::<NetworkTranslation3D>(far).unwrap();
+            assert_eq!(replicated.0.x, expected);
+            let snaps = world.get::<ComponentSnapshots<NetworkTranslation3D>>(far).unwrap();
+            assert_eq!(snaps.frontier_len() + snaps.cache_len(), recorded);
+        }
+        assert!(expected > 0.0);
+    }
+}
@@ -0,0 +1,392 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use bevy_rapier3d::prelude::*;
+use bevy_replicon::prelude::*;
+use super::distance_culling::*;
+use crate::core::*;
+
+#[derive(Clone, Copy)]
+pub struct Occlusion {
+    /// timestamp of latest raycast
+    pub checked_at: f64,
+    /// timestamp entity was in line of sight lastly
+    pub visible_at: f64,
+    /// latest raycast hit
+    pub occluded: bool
+}
+
+impl Occlusion {
+    /// not checked yet, visible until it is
+    #[inline]
+    pub fn new(now: f64) -> Self {
+        Self {
+            checked_at: f64::NEG_INFINITY,
+            visible_at: now,
+            occluded: false
+        }
+    }
+
+    #[inline]
+    pub fn is_check_due(&self, now: f64, check_interval: f64) -> bool {
+        now - self.checked_at >= check_interval
+    }
+
+    /// latest raycast hit and entity is out of sight longer than grace period
+    #[inline]
+    pub fn is_occluded(&self, now: f64, grace_period: f64) -> bool {
+        self.occluded && now - self.visible_at > grace_period
+    }
+}
+
+/// occlusion of entities visible by distance for each player view,
+/// entities left visible ones are dropped so that they start fresh on re-entry
+#[derive(Resource, Default)]
+pub struct OcclusionMap(HashMap<Entity, HashMap<Entity, Occlusion>>);
+
+impl OcclusionMap {
+    #[inline]
+    pub fn get(&self, player: Entity, entity: Entity) -> Option<&Occlusion> {
+        self.0.get(&player)?.get(&entity)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, entity: Entity) {
+        self.0.remove(&entity);
+        for occlusions in self.0.values_mut() {
+            occlusions.remove(&entity);
+        }
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct OcclusionConfig {
+    /// seconds between raycasts of the same pair
+    pub check_interval: f64,
+    /// seconds occluded entity stays visible
+    pub grace_period: f64,
+    /// raycasts per frame at most, rest is checked on next frames
+    pub max_rays_per_frame: usize,
+    /// added to player view translation as ray origin
+    pub eye_offset: Vec3
+}
+
+/// line of sight is blocked only by colliders without `Culling`,
+/// so that players and props do not hide each other
+fn is_occluded(
+    context: &RapierContext,
+    eye: Vec3,
+    target: Vec3,
+    player: Entity,
+    culled: &Query<(), With<Culling>>
+) -> bool {
+    let to_target = target - eye;
+    let distance = to_target.length();
+    if distance <= f32::EPSILON {
+        return false;
+    }
+
+    let predicate = |e: Entity| !culled.contains(e);
+    let filter = QueryFilter::default()
+    .exclude_sensors()
+    .exclude_collider(player)
+    .predicate(&predicate);
+
+    context.cast_ray(eye, to_target / distance, distance, true, filter)
+    .is_some()
+}
+
+/// updates occlusions of one player view and pushes entities to hide.
+/// raycast is done by cast within rays budget shared by player views,
+/// returns false if budget ran out before due checks
+fn update_occlusions(
+    occlusions: &mut HashMap<Entity, Occlusion>,
+    targets: &[(Entity, Vec3)],
+    now: f64,
+    config: &OcclusionConfig,
+    rays: &mut usize,
+    mut cast: impl FnMut(Vec3) -> bool,
+    hidden: &mut Vec<Entity>
+) -> bool {
+    let mut is_done = true;
+    for &(e, target) in targets.iter() {
+        let occlusion = occlusions.entry(e)
+        .or_insert(Occlusion::new(now));
+
+        if occlusion.is_check_due(now, config.check_interval) {
+            if *rays < config.max_rays_per_frame {
+                *rays += 1;
+                occlusion.checked_at = now;
+                occlusion.occluded = cast(target);
+                if !occlusion.occluded {
+                    occlusion.visible_at = now;
+                }
+            } else {
+                is_done = false;
+            }
+        }
+
+        if occlusion.is_occluded(now, config.grace_period) {
+            hidden.push(e);
+        }
+    }
+    is_done
+}
+
+#[allow(clippy::too_many_arguments)]
+fn occlusion_culling_system(
+    query: Query<&Transform, Without<NetworkParent>>,
+    player_views: Query<(Entity, &NetworkEntity, &Transform), With<PlayerView>>,
+    culled: Query<(), With<Culling>>,
+    visible_map: Res<CulledVisibleMap>,
+    mut occlusion_map: ResMut<OcclusionMap>,
+    mut connected_clients: ResMut<ConnectedClients>,
+    context: Res<RapierContext>,
+    config: Res<OcclusionConfig>,
+    clock: Res<NetworkClock>,
+    mut views: Local<Vec<Entity>>,
+    mut first_view: Local<usize>,
+    mut targets: Local<Vec<(Entity, Vec3)>>,
+    mut hidden: Local<Vec<Entity>>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let mut rays = 0;
+
+    // player views removed by other than despawn event
+    occlusion_map.0.retain(|player_e, _| player_views.contains(*player_e));
+
+    // budget is spread round robin, 
+    // view starved on previous frame goes first
+    views.clear();
+    views.extend(player_views.iter().map(|(e, ..)| e));
+    let len = views.len();
+    let start = if len > 0 { *first_view % len } else { 0 };
+    let mut starved = None;
+
+    for i in 0..len {
+        let view_idx = (start + i) % len;
+        let (player_e, player_net_e, player_t) = match player_views.get(views[view_idx]) {
+            Ok(v) => v,
+            Err(_) => continue
+        };
+        let visible = match visible_map.get(player_e) {
+            Some(v) => v,
+            None => {
+                occlusion_map.0.remove(&player_e);
+                continue;
+            }
+        };
+        let visibility = match connected_clients.get_client_mut(player_net_e.client_id()) {
+            Some(c) => c.visibility_mut(),
+            None => {
+                error!("client is not mapped in connected_clients, disconnected?");
+                continue;
+            }
+        };
+        let eye = player_t.translation + config.eye_offset;
+
+        targets.clear();
+        targets.extend(visible.keys()
+            .filter_map(|&e| query.get(e).ok().map(|t| (e, t.translation)))
+        );
+        hidden.clear();
+        let occlusions = occlusion_map.0.entry(player_e)
+        .or_default();
+        // re-entered one starts visible instead of keeping stale visible_at
+        occlusions.retain(|e, _| visible.contains_key(e));
+        let is_done = update_occlusions(
+            occlusions,
+            &targets,
+            now,
+            &config,
+            &mut rays,
+            |target| is_occluded(&context, eye, target, player_e, &culled),
+            &mut hidden
+        );
+        if !is_done && starved.is_none() {
+            starved = Some(view_idx);
+        }
+
+        for &e in hidden.iter() {
+            if visibility.is_visible(e) {
+                debug!("occluded {player_e:?}:{e:?}");
+                visibility.set_visibility(e, false);
+            }
+        }
+    }
+
+    if let Some(idx) = starved {
+        *first_view = idx;
+    }
+}
+
+fn handle_player_entity_event(
+    mut events: EventReader<PlayerEntityEvent>,
+    mut occlusion_map: ResMut<OcclusionMap>
+) {
+    for e in events.read() {
+        if let PlayerEntityEvent::Despawned { client_id: _, entity } = *e {
+            occlusion_map.remove(entity);
+        }
+    }
+}
+
+/// hides entities out of line of sight among visible ones by distance,
+/// requires `DistanceCullingPlugin`
+pub struct OcclusionCullingPlugin {
+    pub check_interval: f64,
+    pub grace_period: f64,
+    pub max_rays_per_frame: usize,
+    pub eye_offset: Vec3,
+    pub auto_clean: bool
+}
+
+impl Plugin for OcclusionCullingPlugin {
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<RepliconServer>() {
+            app.insert_resource(OcclusionMap::default())
+            .insert_resource(OcclusionConfig {
+                check_interval: self.check_interval,
+                grace_period: self.grace_period,
+                max_rays_per_frame: self.max_rays_per_frame,
+                eye_offset: self.eye_offset
+            })
+            .add_systems(PostUpdate,
+                occlusion_culling_system
+                .after(ServerBootSet::Culling)
+                .before(ServerBootSet::Grouping)
+            );
+
+            if self.auto_clean {
+                app.add_systems(PreUpdate,
+                    handle_player_entity_event
+                    .after(ServerBootSet::PlayerEntityEvent)
+                );
+            }
+        } else {
+            panic!("could not find replicon server");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_rays_per_frame: usize) -> OcclusionConfig {
+        OcclusionConfig {
+            check_interval: 0.2,
+            grace_period: 0.5,
+            max_rays_per_frame,
+            eye_offset: Vec3::ZERO
+        }
+    }
+
+    #[test]
+    fn occluded_entity_is_hidden_after_grace_period() {
+        let config = config(8);
+        let wall = Entity::from_raw(1);
+        let targets = vec![(wall, Vec3::X)];
+        let mut occlusions = HashMap::new();
+        let mut hidden = vec![];
+
+        let mut now = 0.0;
+        while now < 0.5 {
+            let mut rays = 0;
+            update_occlusions(
+                &mut occlusions, &targets, now, &config, &mut rays, |_| true, &mut hidden
+            );
+            assert!(hidden.is_empty());
+            now += 0.1;
+        }
+
+        let mut rays = 0;
+        update_occlusions(
+            &mut occlusions, &targets, 0.6, &config, &mut rays, |_| true, &mut hidden
+        );
+        assert_eq!(hidden, vec![wall]);
+
+        // left visible ones and re-entered, starts fresh without raycast
+        occlusions.clear();
+        hidden.clear();
+        let mut rays = config.max_rays_per_frame;
+        update_occlusions(
+            &mut occlusions, &targets, 0.8, &config, &mut rays, |_| true, &mut hidden
+        );
+        assert!(hidden.is_empty());
+    }
+
+    #[test]
+    fn raycasts_are_limited_by_budget() {
+        let config = config(2);
+        let targets: Vec<(Entity, Vec3)> = (0..5)
+        .map(|i| (Entity::from_raw(i), Vec3::X * i as f32))
+        .collect();
+        let mut occlusions = HashMap::new();
+        let mut hidden = vec![];
+        let mut casts = 0;
+
+        let mut rays = 0;
+        update_occlusions(
+            &mut occlusions, &targets, 0.0, &config, &mut rays, 
+            |_| { casts += 1; false }, 
+            &mut hidden
+        );
+        assert_eq!(casts, 2);
+        assert_eq!(rays, 2);
+
+        // unchecked ones are checked on next frame
+        let mut rays = 0;
+        update_occlusions(
+            &mut occlusions, &targets, 0.01, &config, &mut rays, 
+            |_| { casts += 1; false }, 
+            &mut hidden
+        );
+        assert_eq!(casts, 4);
+        let checked = occlusions.values()
+        .filter(|o| o.checked_at.is_finite())
+        .count();
+        assert_eq!(checked, 4);
+    }
+
+    #[test]
+    fn unchecked_entity_stays_visible_on_small_budget() {
+        let config = config(1);
+        let targets: Vec<(Entity, Vec3)> = (0..10)
+        .map(|i| (Entity::from_raw(i), Vec3::X * i as f32))
+        .collect();
+        let mut occlusions = HashMap::new();
+        let mut hidden = vec![];
+
+        // every target is in sight, but one ray per frame 
+        // checks each target only once per second
+        let mut now = 0.0;
+        for _ in 0..20 {
+            let mut rays = 0;
+            let is_done = update_occlusions(
+                &mut occlusions, &targets, now, &config, &mut rays, |_| false, &mut hidden
+            );
+            assert!(!is_done);
+            assert!(hidden.is_empty());
+            now += 0.1;
+        }
+
+        // hit is hidden only after grace period from latest sight
+        let wall = targets[0];
+        for _ in 0..20 {
+            let mut rays = 0;
+            update_occlusions(
+                &mut occlusions, &[wall], now, &config, &mut rays, |_| true, &mut hidden
+            );
+            now += 0.1;
+        }
+        assert_eq!(hidden.last(), Some(&wall.0));
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use bevy_replicon::prelude::*;
+use crate::core::*;
+
+/// children grouped by root, rebuilt on hierarchy change,
+/// with root visibility last applied to them for each client
+#[derive(Default)]
+pub(crate) struct InheritedVisibility {
+    children: HashMap<Entity, Vec<Entity>>,
+    too_deep: Vec<Entity>,
+    applied: HashMap<ClientId, HashMap<Entity, bool>>
+}
+
+/// children are visible to a client only if their root is,
+/// culling only decides visibility of roots
+pub(crate) fn inherit_parent_visibility_system(
+    changed: Query<(), Changed<NetworkParent>>,
+    mut removed: RemovedComponents<NetworkParent>,
+    children: Query<Entity, With<NetworkParent>>,
+    parents: Query<&NetworkParent>,
+    player_views: Query<&NetworkEntity, With<PlayerView>>,
+    mut connected_clients: ResMut<ConnectedClients>,
+    mut inherited: Local<InheritedVisibility>
+) {
+    let InheritedVisibility { children: roots, too_deep, applied } = &mut *inherited;
+    if !changed.is_empty() || removed.read().count() > 0 {
+        roots.clear();
+        too_deep.clear();
+        // re-parented children are applied again
+        applied.clear();
+
+        for e in children.iter() {
+            match network_root(e, &parents) {
+                Some(root) => roots.entry(root)
+                .or_default()
+                .push(e),
+                None => {
+                    warn!("hierarchy of: {e:?} is deeper than {MAX_HIERARCHY_DEPTH}");
+                    too_deep.push(e);
+                }
+            }
+        }
+    }
+
+    applied.retain(|client_id, _| {
+        player_views.iter()
+        .any(|net_e| net_e.client_id() == *client_id)
+    });
+
+    for player_net_e in player_views.iter() {
+        let client_id = player_net_e.client_id();
+        let visibility = match connected_clients.get_client_mut(client_id) {
+            Some(c) => c.visibility_mut(),
+            None => {
+                error!("client is not mapped in connected_clients, disconnected?");
+                continue;
+            }
+        };
+
+        let applied = applied.entry(client_id)
+        .or_insert_with(|| {
+            for &e in too_deep.iter() {
+                visibility.set_visibility(e, false);
+            }
+            default()
+        });
+
+        for (&root, root_children) in roots.iter() {
+            let is_visible = visibility.is_visible(root);
+            if applied.insert(root, is_visible) == Some(is_visible) {
+                continue;
+            }
+
+            for &e in root_children.iter() {
+                if visibility.is_visible(e) != is_visible {
+                    visibility.set_visibility(e, is_visible);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_follow_root_after_reparenting() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            RepliconPlugins.set(ServerPlugin {
+                visibility_policy: VisibilityPolicy::Whitelist,
+                ..default()
+            })
+        ))
+        .add_systems(PostUpdate, inherit_parent_visibility_system);
+
+        let client_id = ClientId::new(1);
+        app.world.resource_mut::<RepliconServer>()
+        .set_running(true);
+        app.world.send_event(ServerEvent::ClientConnected { client_id });
+        app.update();
+
+        app.world.spawn((NetworkEntity::new(client_id), PlayerView::new()));
+        let visible_root = app.world.spawn_empty().id();
+        let hidden_root = app.world.spawn_empty().id();
+        let child = app.world.spawn(NetworkParent::new(visible_root)).id();
+        let grandchild = app.world.spawn(NetworkParent::new(child)).id();
+        app.world.resource_mut::<ConnectedClients>()
+        .client_mut(client_id)
+        .visibility_mut()
+        .set_visibility(visible_root, true);
+
+        let is_visible = |app: &App, e: Entity| app.world.resource::<ConnectedClients>()
+        .client(client_id)
+        .visibility()
+        .is_visible(e);
+
+        app.update();
+        assert!(is_visible(&app, child));
+        assert!(is_visible(&app, grandchild));
+
+        app.world.entity_mut(child)
+        .insert(NetworkParent::new(hidden_root));
+        app.update();
+        assert!(!is_visible(&app, child));
+        assert!(!is_visible(&app, grandchild));
+
+        // root turning visible is applied without hierarchy change
+        app.world.resource_mut::<ConnectedClients>()
+        .client_mut(client_id)
+        .visibility_mut()
+        .set_visibility(hidden_root, true);
+        app.update();
+        assert!(is_visible(&app, child));
+        assert!(is_visible(&app, grandchild));
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet}
+};
+
+/// uniform grid of culled entities keyed by cell.
+/// entities visible farther than cell size are kept out of cells,
+/// so that one of them does not widen every query
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec3, HashSet<Entity>>,
+    /// cell and radius entity is visible within
+    entities: HashMap<Entity, (IVec3, f32)>,
+    oversized: HashSet<Entity>,
+    max_radius: f32,
+    max_radius_dirty: bool
+}
+
+impl SpatialGrid {
+    #[inline]
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entities: HashMap::new(),
+            oversized: HashSet::new(),
+            max_radius: 0.0,
+            max_radius_dirty: false
+        }
+    }
+
+    #[inline]
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    #[inline]
+    pub fn cell(&self, translation: Vec3) -> IVec3 {
+        (translation / self.cell_size).floor().as_ivec3()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len() + self.oversized.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// radius is infinite for entities visible from any distance
+    pub fn insert(&mut self, entity: Entity, translation: Vec3, radius: f32) {
+        if radius > self.cell_size {
+            self.remove(entity);
+            self.oversized.insert(entity);
+            return;
+        }
+
+        let cell = self.cell(translation);
+        match self.entities.get_mut(&entity) {
+            Some((c, r)) if *c == cell => {
+                if *r >= self.max_radius && radius < *r {
+                    self.max_radius_dirty = true;
+                }
+                *r = radius;
+                self.max_radius = self.max_radius.max(radius);
+                return;
+            }
+            _ => self.remove(entity)
+        }
+
+        self.cells.entry(cell)
+        .or_default()
+        .insert(entity);
+        self.entities.insert(entity, (cell, radius));
+        self.max_radius = self.max_radius.max(radius);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.oversized.remove(&entity);
+
+        let (cell, radius) = match self.entities.remove(&entity) {
+            Some(c) => c,
+            None => return
+        };
+        if radius >= self.max_radius {
+            self.max_radius_dirty = true;
+        }
+        if let Some(set) = self.cells.get_mut(&cell) {
+            set.remove(&entity);
+            if set.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// recomputes max radius if largest one was removed or shrunk,
+    /// call once after updates
+    pub fn refresh_max_radius(&mut self) {
+        if !self.max_radius_dirty {
+            return;
+        }
+
+        self.max_radius = self.entities.values()
+        .map(|(_, r)| *r)
+        .fold(0.0, f32::max);
+        self.max_radius_dirty = false;
+    }
+
+    /// largest radius of entities in cells, at most cell size
+    #[inline]
+    pub fn max_radius(&self) -> f32 {
+        self.max_radius
+    }
+
+    /// entities visible farther than cell size, checked on every query
+    #[inline]
+    pub fn oversized(&self) -> impl Iterator<Item = &Entity> {
+        self.oversized.iter()
+    }
+
+    /// pushes entities in cells overlapping the cube around center,
+    /// they still need exact distance check
+    pub fn query(&self, center: Vec3, radius: f32, out: &mut Vec<Entity>) {
+        if radius < 0.0 {
+            return;
+        }
+
+        let min = self.cell(center - Vec3::splat(radius));
+        let max = self.cell(center + Vec3::splat(radius));
+        let span = (max - min + IVec3::ONE).as_i64vec3();
+        // range covers more cells than occupied, scan occupied ones
+        if span.x * span.y * span.z > self.cells.len() as i64 {
+            for (cell, set) in self.cells.iter() {
+                if cell.cmpge(min).all() && cell.cmple(max).all() {
+                    out.extend(set.iter());
+                }
+            }
+            return;
+        }
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    if let Some(set) = self.cells.get(&IVec3::new(x, y, z)) {
+                        out.extend(set.iter());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_returns_nearby_cells_only() {
+        let mut grid = SpatialGrid::new(10.0);
+        let near = Entity::from_raw(0);
+        let far = Entity::from_raw(1);
+        let unbounded = Entity::from_raw(2);
+        let oversized = Entity::from_raw(3);
+        grid.insert(near, Vec3::new(5.0, 0.0, 5.0), 10.0);
+        grid.insert(far, Vec3::new(500.0, 0.0, 5.0), 10.0);
+        grid.insert(unbounded, Vec3::ZERO, f32::INFINITY);
+        grid.insert(oversized, Vec3::new(500.0, 0.0, 500.0), 1000.0);
+        assert_eq!(grid.len(), 4);
+        assert_eq!(grid.max_radius(), 10.0);
+
+        let mut out = vec![];
+        grid.query(Vec3::new(12.0, 0.0, 0.0), 10.0, &mut out);
+        assert_eq!(out, vec![near]);
+
+        // moved across cells
+        grid.insert(far, Vec3::new(15.0, 0.0, 0.0), 10.0);
+        out.clear();
+        grid.query(Vec3::new(12.0, 0.0, 0.0), 10.0, &mut out);
+        out.sort();
+        assert_eq!(out, vec![near, far]);
+
+        grid.remove(near);
+        out.clear();
+        grid.query(Vec3::new(12.0, 0.0, 0.0), 10.0, &mut out);
+        assert_eq!(out, vec![far]);
+        assert_eq!(grid.oversized().count(), 2);
+
+        grid.insert(far, Vec3::new(15.0, 0.0, 0.0), 5.0);
+        grid.refresh_max_radius();
+        assert_eq!(grid.max_radius(), 5.0);
+    }
+}
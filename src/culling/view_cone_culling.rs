@@ -0,0 +1,237 @@
+use serde::{Serialize, Deserialize};
+use bevy::{
+    prelude::*,
+    utils::HashMap
+};
+use bevy_replicon::prelude::*;
+use super::distance_culling::*;
+use crate::core::*;
+
+/// camera direction client sends to override facing of its view cone,
+/// None clears it and facing follows rotation again
+#[derive(Event, Serialize, Deserialize, Clone, Copy)]
+pub struct ViewDirection(Option<Vec3>);
+
+impl ViewDirection {
+    #[inline]
+    pub fn new(direction: Vec3) -> Self {
+        Self(Some(direction))
+    }
+
+    #[inline]
+    pub fn clear() -> Self {
+        Self(None)
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<Vec3> {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ViewConeMode {
+    /// hides entities out of view cone for longer than grace period,
+    /// so that turning around does not respawn them on client
+    Hide {
+        grace_period: f64
+    },
+    /// multiplies squared distance of entities out of view cone,
+    /// so that `NetworkLod` replicates them less frequently
+    Deprioritise {
+        multiplier: f32
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct ViewConeConfig {
+    pub mode: ViewConeMode,
+    /// squared distance entities stay relevant within regardless of facing
+    pub near_threshold: f32,
+    /// seconds direction sent by client overrides rotation
+    pub facing_timeout: f64
+}
+
+/// timestamp entities were in view cone lastly for each player view
+#[derive(Resource, Default)]
+pub struct InViewMap(HashMap<Entity, HashMap<Entity, f64>>);
+
+impl InViewMap {
+    #[inline]
+    pub fn get(&self, player: Entity, entity: Entity) -> Option<f64> {
+        self.0.get(&player)?.get(&entity).copied()
+    }
+
+    #[inline]
+    pub fn remove(&mut self, entity: Entity) {
+        self.0.remove(&entity);
+        for in_view in self.0.values_mut() {
+            in_view.remove(&entity);
+        }
+    }
+}
+
+fn handle_view_direction_system(
+    mut events: EventReader<FromClient<ViewDirection>>,
+    mut player_views: Query<(&NetworkEntity, &mut PlayerView)>,
+    clock: Res<NetworkClock>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+
+    for FromClient { client_id, event } in events.read() {
+        let direction = event.get();
+        if let Some(d) = direction {
+            if !d.is_finite() || d == Vec3::ZERO {
+                warn!("discarding: malformatted view direction from: {client_id:?}");
+                continue;
+            }
+        }
+
+        let view = player_views.iter_mut()
+        .find(|(net_e, _)| net_e.client_id() == *client_id);
+        let mut view = match view {
+            Some((_, v)) => v,
+            None => continue
+        };
+
+        if let Some(cone) = view.cone_mut() {
+            cone.set_facing(direction, now);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn view_cone_culling_system(
+    query: Query<&Transform, Without<NetworkParent>>,
+    player_views: Query<(Entity, &NetworkEntity, &Transform, &PlayerView)>,
+    visible_map: Res<CulledVisibleMap>,
+    mut distance_map: ResMut<DistanceMap>,
+    mut in_view_map: ResMut<InViewMap>,
+    mut connected_clients: ResMut<ConnectedClients>,
+    config: Res<ViewConeConfig>,
+    clock: Res<NetworkClock>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+
+    in_view_map.0.retain(|player_e, _| player_views.contains(*player_e));
+
+    for (player_e, player_net_e, player_t, view) in player_views.iter() {
+        let cone = match view.cone() {
+            Some(c) => c,
+            None => continue
+        };
+        let visible = match visible_map.get(player_e) {
+            Some(v) => v,
+            None => continue
+        };
+        let visibility = match connected_clients.get_client_mut(player_net_e.client_id()) {
+            Some(c) => c.visibility_mut(),
+            None => {
+                error!("client is not mapped in connected_clients, disconnected?");
+                continue;
+            }
+        };
+        let facing = cone.facing(player_t.rotation, now, config.facing_timeout);
+        let in_view = in_view_map.0.entry(player_e)
+        .or_default();
+        // entering again by distance starts in view
+        in_view.retain(|e, _| visible.contains_key(e));
+
+        for &e in visible.keys() {
+            let target = match query.get(e) {
+                Ok(t) => t.translation,
+                Err(_) => continue
+            };
+            let to_target = target - player_t.translation;
+            let distance = to_target.length_squared();
+            let in_view_at = in_view.entry(e)
+            .or_insert(now);
+            if distance < config.near_threshold
+            || cone.contains(facing, to_target) {
+                *in_view_at = now;
+                continue;
+            }
+
+            match config.mode {
+                ViewConeMode::Hide { grace_period } => {
+                    if now - *in_view_at > grace_period
+                    && visibility.is_visible(e) {
+                        debug!("out of view cone {player_e:?}:{e:?}");
+                        visibility.set_visibility(e, false);
+                    }
+                }
+                ViewConeMode::Deprioritise { multiplier } => {
+                    // set from raw distance, so it does not compound over frames
+                    if let Some(d) = distance_map.get_mut(player_e, e) {
+                        d.distance = distance * multiplier;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn handle_player_entity_event(
+    mut events: EventReader<PlayerEntityEvent>,
+    mut in_view_map: ResMut<InViewMap>
+) {
+    for e in events.read() {
+        if let PlayerEntityEvent::Despawned { client_id: _, entity } = *e {
+            in_view_map.remove(entity);
+        }
+    }
+}
+
+/// culls entities behind player views with `ViewCone`,
+/// requires `DistanceCullingPlugin` on server
+pub struct ViewConeCullingPlugin {
+    pub mode: ViewConeMode,
+    /// squared distance entities stay relevant within regardless of facing
+    pub near_threshold: f32,
+    /// seconds direction sent by client overrides rotation
+    pub facing_timeout: f64,
+    pub auto_clean: bool
+}
+
+impl Plugin for ViewConeCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_client_event::<ViewDirection>(ChannelKind::Unreliable);
+
+        if app.world.contains_resource::<RepliconServer>() {
+            app.insert_resource(ViewConeConfig {
+                mode: self.mode,
+                near_threshold: self.near_threshold,
+                facing_timeout: self.facing_timeout
+            })
+            .insert_resource(InViewMap::default())
+            .add_systems(PostUpdate, (
+                handle_view_direction_system
+                .before(ServerBootSet::Culling),
+                view_cone_culling_system
+                .after(culling_system)
+                .in_set(ServerBootSet::Culling)
+            ));
+
+            if self.auto_clean {
+                app.add_systems(PreUpdate,
+                    handle_player_entity_event
+                    .after(ServerBootSet::PlayerEntityEvent)
+                );
+            }
+        } else if !app.world.contains_resource::<RepliconClient>() {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
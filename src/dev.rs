@@ -28,13 +28,27 @@ impl Plugin for GameCommonPlugin {
                     rotation: RotationAxis::Y
                 },
                 interpolation_config: InterpolationConfig { 
-                    network_tick_delta: DEV_NETWORK_TICK_DELTA64 
+                    network_tick_delta: DEV_NETWORK_TICK_DELTA64,
+                    mode: InterpolationMode::Extrapolate { 
+                        max_time: DEV_MAX_EXTRAPOLATION_SECONDS 
+                    },
+                    timeline: InterpolationTimeline::AdaptiveServerTick { 
+                        min_delay_ticks: DEV_MIN_INTERPOLATION_DELAY_TICKS,
+                        max_delay_ticks: DEV_MAX_INTERPOLATION_DELAY_TICKS 
+                    },
+                    strategy: InterpolationStrategy::Hermite
                 },
                 prediction_config: PredictionConfig { 
                     translation_threshold: TRANSLATION_ERROR_THRESHOLD, 
                     rotation_threshold: ROTATION_ERROR_THRESHOLD, 
                     force_replicate_error_count: PREDICTION_ERROR_COUNT_THRESHOLD 
                 },
+                max_rewind: MaxRewind {
+                    window: RewindWindow::Duration { 
+                        seconds: DEV_MAX_REWIND_SECONDS 
+                    },
+                    on_exceeded: RewindExceeded::Reject
+                }
             },
             Rapier3DPlugin{
                 delta_time: PHYSICS_FIXED_TICK_DELTA,
@@ -45,14 +59,31 @@ impl Plugin for GameCommonPlugin {
             NetworkTranslationPlugin::<
                 NetworkCharacterController,
                 NetworkMovement2_5D
-            >::new(),
+            >::hermite(),
             NetworkRotationPlugin::<
                 NetworkAngle,
                 NetworkMovement2_5D
+            >::hermite(),
+            NetworkRollbackPlugin::<
+                NetworkCharacterController,
+                NetworkAngle,
+                NetworkMovement2_5D,
+                PlayerMovementParams
+            >::new(),
+            NetworkTeleportPlugin::<
+                NetworkCharacterController,
+                NetworkAngle,
+                NetworkMovement2_5D
             >::new(),
+            NetworkHierarchyPlugin,
 
             ClientEventPlugin::<NetworkMovement2_5D>::new(ChannelKind::Unreliable),
             ClientEventPlugin::<NetworkFire>::new(ChannelKind::Ordered),
+            InputAckPlugin::<NetworkMovement2_5D>::new(),
+            ClockSyncPlugin{
+                ping_interval: DEV_CLOCK_SYNC_INTERVAL,
+                sample_size: DEV_CLOCK_SYNC_SAMPLE_SIZE
+            }
         ))
         .replicate::<PlayerPresentation>()
         .add_systems(FixedUpdate,
@@ -114,6 +145,60 @@ pub struct PlayerMovementParams {
     pub base_angular_speed: f32,
 }
 
+impl PlayerMovementParams {
+    #[inline]
+    pub fn rotate(
+        &self, 
+        transform: &mut Transform, 
+        movement: &NetworkMovement2_5D, 
+        delta_seconds: f32
+    ) {
+        if movement.rotation_axis != Vec2::ZERO {
+            let mut angle = movement.rotation_axis.x;
+            angle *= self.base_angular_speed * delta_seconds;
+
+            transform.rotate_y(-angle.to_radians());
+        }
+    }
+
+    #[inline]
+    pub fn translation_delta(
+        &self, 
+        rotation: Quat, 
+        movement: &NetworkMovement2_5D, 
+        delta_seconds: f32
+    ) -> Vec3 {
+        if movement.linear_axis == Vec2::ZERO {
+            return Vec3::ZERO;
+        }
+
+        let axis = Vec3::new(
+            movement.linear_axis.x, 
+            0.0, 
+            -movement.linear_axis.y
+        ).normalize();
+        
+        let dir = (rotation * axis)
+        .normalize();
+
+        dir * (self.base_speed * delta_seconds)
+    }
+}
+
+/// same rotation and translation as `update_character_controller_system` in a tick,
+/// but not resolved by the controller, replay passes through colliders
+impl MovementStep<NetworkMovement2_5D> for PlayerMovementParams {
+    #[inline]
+    fn step(&self, transform: &mut Transform, movement: &NetworkMovement2_5D) {
+        self.rotate(transform, movement, PHYSICS_FIXED_TICK_DELTA);
+        transform.translation += self.translation_delta(
+            transform.rotation, 
+            movement, 
+            PHYSICS_FIXED_TICK_DELTA
+        );
+    }
+}
+
 #[derive(Event, Serialize, Deserialize, Clone)]
 pub struct NetworkFire {
     pub index: usize,
@@ -165,25 +250,14 @@ pub fn update_character_controller_system(
             };
 
             let movement = snap.event();
-
-            if movement.rotation_axis != Vec2::ZERO {
-                let mut angle = movement.rotation_axis.x;
-                angle *= params.base_angular_speed * time.delta_seconds();
-
-                transform.rotate_y(-angle.to_radians());
-            }
+            params.rotate(&mut transform, movement, time.delta_seconds());
 
             if movement.linear_axis != Vec2::ZERO {
-                let axis = Vec3::new(
-                    movement.linear_axis.x, 
-                    0.0, 
-                    -movement.linear_axis.y
-                ).normalize();
-                
-                let dir = (transform.rotation * axis)
-                .normalize();
-
-                d += dir * (params.base_speed * time.delta_seconds());
+                d += params.translation_delta(
+                    transform.rotation, 
+                    movement, 
+                    time.delta_seconds()
+                );
                 cc.translation = Some(d);
             }
         }
@@ -15,6 +15,19 @@ pub const DEV_TOKEN_EXPIRE_SEC: u64 = 300;
 pub const DEV_MAX_UPDATE_SNAPSHOT_SIZE: usize = 2560;
 pub const DEV_MAX_SNAPSHOT_SIZE: usize = 64;
 
+pub const DEV_CLOCK_SYNC_INTERVAL: f64 = 1.0;
+pub const DEV_CLOCK_SYNC_SAMPLE_SIZE: usize = 8;
+
+pub const DEV_SMOOTHING_RATE: f32 = 10.0;
+pub const DEV_SMOOTHING_SNAP_DISTANCE: f32 = 5.0;
+
+pub const DEV_FIRE_RANGE: f32 = 100.0;
+pub const DEV_MAX_REWIND_SECONDS: f64 = 1.0;
+pub const DEV_MAX_EXTRAPOLATION_SECONDS: f64 = 0.25;
+pub const DEV_MIN_INTERPOLATION_DELAY_TICKS: u32 = 1;
+pub const DEV_MAX_INTERPOLATION_DELAY_TICKS: u32 = 6;
+pub const DEV_FALL_LIMIT: f32 = -20.0;
+
 pub const BASE_SPEED: f32 = 10.0;
 pub const BASE_ANGULAR_SPEED: f32 = 5.0; 
 
@@ -26,7 +39,15 @@ pub const ROTATION_ERROR_THRESHOLD: f32 = 10.0;
 // 1sec / network tick
 pub const PREDICTION_ERROR_COUNT_THRESHOLD: u32 = 10;
 
-pub const DISTANCE_CULLING_THREASHOLD: f32 = 50.0;
+// compared with squared distance
+pub const DISTANCE_CULLING_THREASHOLD: f32 = 50.0 * 50.0;
+pub const DISTANCE_CULLING_EXIT_THREASHOLD: f32 = 55.0 * 55.0;
+pub const DISTANCE_CULLING_MIN_VISIBLE_SECONDS: f64 = 1.0;
+pub const DISTANCE_CULLING_CELL_SIZE: f32 = 55.0;
+
+pub const DEV_OCCLUSION_CHECK_INTERVAL: f64 = 0.2;
+pub const DEV_OCCLUSION_GRACE_PERIOD: f64 = 0.5;
+pub const DEV_OCCLUSION_MAX_RAYS_PER_FRAME: usize = 256;
 
 pub fn get_dev_protocol_id() -> u64 {
     if cfg!(debug_assertions) {
@@ -1,7 +1,4 @@
-use bevy::{
-    utils::SystemTime,
-    input::mouse::MouseMotion 
-};
+use bevy::input::mouse::MouseMotion;
 use bevy_replicon::client::confirm_history::ConfirmHistory;
 use bevy_rapier3d::prelude::*;
 use super::{
@@ -28,6 +25,12 @@ pub struct GameClientPlugin;
 impl Plugin for GameClientPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(GameCommonPlugin)
+        .add_plugins(CorrectionSmoothingPlugin{
+            mode: SmoothingMode::Exponential { 
+                rate: DEV_SMOOTHING_RATE 
+            },
+            snap_distance: DEV_SMOOTHING_SNAP_DISTANCE
+        })
         .insert_resource(KeyboardInputActionMap{
             movement_up: KeyCode::KeyW,
             movement_left: KeyCode::KeyA,
@@ -118,14 +121,13 @@ fn handle_action(
     query: Query<&Transform, With<Owning>>,
     mut actions: EventReader<Action>,
     mut movements: EventWriter<NetworkMovement2_5D>,
-    mut fires: EventWriter<NetworkFire>
+    mut fires: EventWriter<NetworkFire>,
+    clock: Res<NetworkClock>
 ) {
     if let Ok(transform) = query.get_single() {
         for (a, event_id) in actions.read_with_id() {
-            let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("sytem time looks earlier than unix epoch")
-            .as_secs_f64();
+            let timestamp = clock.now()
+            .expect("sytem time looks earlier than unix epoch");
 
             if a.has_movement() {
                 let mut bits = 0;
@@ -173,8 +175,12 @@ fn handle_player_spawned(
     ), 
         Added<NetworkEntity>
     >,
-    client: Res<Client>
+    client: Res<Client>,
+    clock: Res<NetworkClock>
 ) {
+    let timestamp = clock.now()
+    .expect("sytem time looks earlier than unix epoch");
+
     for (
         e, net_e, 
         presentation, 
@@ -185,19 +191,33 @@ fn handle_player_spawned(
         let tick = confirmed_tick.last_tick()
         .get();
 
-        commands.entity(e)
-        .insert((
+        let is_owning = net_e.client_id()
+        .get() == client.id();
+
+        // mesh is rendered on child so that it can be smoothed 
+        // and interpolated without moving collider
+        let visual = commands.spawn((
             PbrBundle{
                 mesh: meshes.add(Mesh::from(Capsule3d::new(
                     CHARACTER_RADIUS, 
                     CHARACTER_HALF_HIGHT * 2.0
                 ))),
                 material: materials.add(presentation.color),
-                transform: Transform{
-                    translation: net_trans.to_vec3(TranslationAxis::XZ),
-                    rotation: net_rot.to_quat(RotationAxis::Y),
-                    scale: Vec3::ONE
-                },
+                ..default()
+            },
+            SmoothedVisual
+        ))
+        .id();
+
+        let transform = Transform{
+            translation: net_trans.to_vec3(TranslationAxis::XZ),
+            rotation: net_rot.to_quat(RotationAxis::Y),
+            scale: Vec3::ONE
+        };
+        commands.entity(e)
+        .insert((
+            SpatialBundle{
+                transform,
                 ..default()
             },
 
@@ -205,27 +225,33 @@ fn handle_player_spawned(
             ComponentSnapshots::with_init(
                 *net_trans, 
                 tick, 
+                timestamp,
                 DEV_MAX_SNAPSHOT_SIZE
-            ).expect("sytem time looks earlier than unix epoch"),
+            ).expect("failed to initialize translation snapshots"),
             ComponentSnapshots::with_init(
                 *net_rot, 
                 tick, 
+                timestamp,
                 DEV_MAX_SNAPSHOT_SIZE
-            ).expect("sytem time looks earlier than unix epoch")
-        ));
+            ).expect("failed to initialize rotation snapshots")
+        ))
+        .add_child(visual);
 
-        if net_e.client_id()
-        .get() == client.id() {
+        if is_owning {
             commands.entity(e)
             .insert((
                 Owning,
+                CorrectionSmoothing::default(),
                 CharacterControllerBundle::default(),
                 EventSnapshots::<NetworkFire>::with_capacity(DEV_MAX_SNAPSHOT_SIZE),
                 EventSnapshots::<NetworkMovement2_5D>::with_capacity(DEV_MAX_SNAPSHOT_SIZE)
             ));
         } else {
             commands.entity(e)
-            .insert(RigidBody::KinematicPositionBased);
+            .insert((
+                RigidBody::KinematicPositionBased,
+                VisualTransform::new(&transform)
+            ));
         }
 
         info!("player: {:?} spawned at tick: {}", net_e.client_id(), tick);
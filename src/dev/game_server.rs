@@ -23,17 +23,35 @@ impl Plugin for GameServerPlugin {
         .add_plugins((
             DefaultPlayerEntityEventPlugin,
             DistanceCullingPlugin{
-                culling_threshold: DISTANCE_CULLING_THREASHOLD, 
+                enter_threshold: DISTANCE_CULLING_THREASHOLD, 
+                exit_threshold: DISTANCE_CULLING_EXIT_THREASHOLD,
+                min_visible_duration: DISTANCE_CULLING_MIN_VISIBLE_SECONDS,
+                cell_size: DISTANCE_CULLING_CELL_SIZE,
                 auto_clean: true
             },
-            RelevantGroupPlugin::<PlayerGroup>::new()
+            OcclusionCullingPlugin {
+                check_interval: DEV_OCCLUSION_CHECK_INTERVAL,
+                grace_period: DEV_OCCLUSION_GRACE_PERIOD,
+                max_rays_per_frame: DEV_OCCLUSION_MAX_RAYS_PER_FRAME,
+                eye_offset: Vec3::Y * CHARACTER_HALF_HIGHT,
+                auto_clean: true
+            },
+            RelevantGroupPlugin::<PlayerGroup>::new(),
+            LagCompensationPlugin::<NetworkCharacterController, NetworkAngle>::new()
         ))
         .add_systems(Update, (
             handle_transport_error,
             handle_server_event,
             handle_player_entity_event,
-            handle_fire
-        ).chain());
+            handle_rewind_rejected,
+            handle_fire,
+            handle_fallen_player
+        ).chain(
+        ).before(ServerBootSet::LagCompensation))
+        .add_systems(Update,
+            handle_lag_compensated_hit
+            .after(ServerBootSet::LagCompensation)
+        );
     }
 }
 
@@ -79,10 +97,13 @@ fn handle_player_entity_event(
     mut events: EventReader<PlayerEntityEvent>,
     mut start_lines: ResMut<PlayerStartLines>,
     server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>
 ) {
     for e in events.read() {
         if let PlayerEntityEvent::Spawned { client_id, entity } = e {
             let tick = server_tick.get();
+            let timestamp = clock.now()
+            .expect("sytem time looks earlier than unix epoch");
             let group = PlayerGroup::random();
             let player_start = start_lines.next(0)
             .expect("missing player start lines initialization");
@@ -91,8 +112,9 @@ fn handle_player_entity_event(
             commands.entity(*entity)
             .insert((
                 PlayerPresentation::random(),
-                PlayerView,
+                PlayerView::new(),
                 Culling::default(),
+                TeleportCounter::default(),
                 group,
                 TransformBundle::from_transform(
                     Transform::from_translation(player_start.translation)
@@ -103,14 +125,16 @@ fn handle_player_entity_event(
                     player_start.translation,
                     default(), 
                     tick, 
+                    timestamp,
                     DEV_MAX_UPDATE_SNAPSHOT_SIZE
-                ).expect("sytem time looks earlier than unix epoch"),
+                ).expect("failed to initialize translation snapshots"),
                 NetworkRotationBundle::<NetworkAngle>::new(
                     default(), 
                     RotationAxis::Z,
                     tick, 
+                    timestamp,
                     DEV_MAX_UPDATE_SNAPSHOT_SIZE
-                ).expect("sytem time looks earlier than unix epoch"),
+                ).expect("failed to initialize rotation snapshots"),
                 EventSnapshots::<NetworkMovement2_5D>::with_capacity(
                     DEV_MAX_UPDATE_SNAPSHOT_SIZE
                 ),
@@ -122,61 +146,101 @@ fn handle_player_entity_event(
     }
 }
 
+fn handle_rewind_rejected(mut rejections: EventReader<RewindRejected>) {
+    for r in rejections.read() {
+        warn!(
+            "player: {:?} rewind to: {} exceeded: {}, {:?}",
+            r.client_id,
+            r.timestamp,
+            r.bound,
+            r.action
+        );
+    }
+}
+
 fn handle_fire(
     mut shooters: Query<(
+        Entity,
         &NetworkEntity, 
+        &Transform,
         &mut EventSnapshots<NetworkFire>
     )>,
-    query: Query<(
-        &NetworkEntity, 
-        &ComponentSnapshots<NetworkCharacterController>
-    )>,
+    mut queries: EventWriter<LagCompensatedQuery>
 ) {
-    for (shooter, mut fire_snaps) in shooters.iter_mut() {
+    for (entity, shooter, transform, mut fire_snaps) in shooters.iter_mut() {
         for fire in fire_snaps.frontier_ref() {
             info!(
                 "player: {:?} fired at {}",
                 shooter.client_id(), 
                 fire.timestamp() 
             );
-    
-            for (net_e, snaps) in query.iter() {
-                let is_shooter = net_e.client_id() == shooter.client_id();
-    
-                let cache = snaps.cache_ref();
-                let index = match cache.iter()
-                .rposition(|s| 
-                    s.timestamp() <= fire.timestamp()
-                ) {
-                    Some(idx) => idx,
-                    None => {
-                        if cfg!(debug_assertions) {
-                            panic!(
-                                "could not find timestamp smaller than {}",
-                                fire.timestamp()
-                            );
-                        } else {
-                            warn!(
-                                "could not find timestamp smaller than {}, skipping",
-                                fire.timestamp()
-                            );
-                            continue;
-                        }
-                    }
-                };
-    
-                // get by found index
-                let snap = cache.get(index).unwrap();
-                info!(
-                    "found latest snap: shooter: {}, index: {}, timestamp: {}, translation: {}",
-                    is_shooter, 
-                    index, 
-                    snap.timestamp(), 
-                    snap.component().0
-                );
-            }
+
+            queries.send(LagCompensatedQuery {
+                shooter: entity,
+                client_id: shooter.client_id(),
+                timestamp: fire.timestamp(),
+                cast: LagCompensatedCast::Ray {
+                    origin: transform.translation,
+                    direction: *transform.forward(),
+                    max_toi: DEV_FIRE_RANGE
+                }
+            });
         }
 
         fire_snaps.cache();
     }
 }
+
+fn handle_fallen_player(
+    query: Query<(Entity, &Transform), With<NetworkEntity>>,
+    mut start_lines: ResMut<PlayerStartLines>,
+    mut teleports: EventWriter<Teleport>
+) {
+    for (entity, transform) in query.iter() {
+        if transform.translation.y > DEV_FALL_LIMIT {
+            continue;
+        }
+
+        let player_start = match start_lines.next(0) {
+            Some(s) => s,
+            None => {
+                warn!("missing player start lines initialization");
+                continue;
+            }
+        };
+
+        teleports.send(Teleport {
+            entity,
+            translation: player_start.translation,
+            rotation: None
+        });
+    }
+}
+
+fn handle_lag_compensated_hit(
+    mut hits: EventReader<LagCompensatedHit>,
+    query: Query<&NetworkEntity>
+) {
+    for hit in hits.read() {
+        let shooter = match query.get(hit.shooter) {
+            Ok(e) => e.client_id(),
+            Err(_) => continue
+        };
+
+        match query.get(hit.target) {
+            Ok(target) => info!(
+                "player: {:?} hit player: {:?} at: {} view timestamp: {}",
+                shooter,
+                target.client_id(),
+                hit.point,
+                hit.view_timestamp
+            ),
+            Err(_) => debug!(
+                "player: {:?} hit entity: {:?} at: {}",
+                shooter,
+                hit.target,
+                hit.point
+            )
+        }
+    }
+}
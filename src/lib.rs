@@ -27,6 +27,7 @@ pub struct NetworkBootPlugin {
     pub transform_axis: TransformAxis,
     pub interpolation_config: InterpolationConfig,
     pub prediction_config: PredictionConfig,
+    pub max_rewind: MaxRewind
 }
 
 impl Plugin for NetworkBootPlugin {
@@ -34,6 +35,9 @@ impl Plugin for NetworkBootPlugin {
         app.insert_resource(self.transform_axis.clone())
         .insert_resource(self.interpolation_config.clone())
         .insert_resource(self.prediction_config.clone())
+        .insert_resource(self.max_rewind.clone())
+        .add_event::<RewindRejected>()
+        .init_resource::<NetworkClock>()
         .configure_sets(PreUpdate, 
             ClientBootSet::UnboxReplication
             .after(ClientSet::Receive)
@@ -51,9 +55,13 @@ impl Plugin for NetworkBootPlugin {
             .before(ClientSet::Send)
         )
         .configure_sets(PreUpdate, 
-            ServerBootSet::UnboxEvent
+            ServerBootSet::ClockSync
             .after(ServerSet::Receive)
         )
+        .configure_sets(PreUpdate, 
+            ServerBootSet::UnboxEvent
+            .after(ServerBootSet::ClockSync)
+        )
         .configure_sets(PreUpdate, 
             ServerBootSet::PlayerEntityEvent
             .after(ServerSet::Receive)
@@ -82,7 +90,18 @@ impl Plugin for NetworkBootPlugin {
             ServerBootSet::ApplyLocalChange
             .before(ServerBootSet::Cache)
         )
-        .replicate::<NetworkEntity>();
+        .replicate::<NetworkEntity>()
+        .replicate::<TeleportCounter>();
+
+        if app.world.contains_resource::<RepliconClient>() {
+            app.init_resource::<ServerTickEstimate>()
+            .insert_resource(RenderDelay::from_config(&self.interpolation_config))
+            .add_systems(PreUpdate, (
+                estimate_server_tick_system,
+                adapt_render_delay_system
+            ).chain(
+            ).in_set(ClientBootSet::UnboxReplication));
+        }
     }
 }
 
@@ -103,7 +122,10 @@ impl Plugin for DefaultPlayerEntityEventPlugin {
     }
 }
 
-pub struct NetworkTranslationPlugin<T, E>(PhantomData<T>, PhantomData<E>)
+pub struct NetworkTranslationPlugin<T, E>(
+    Option<HermiteInterpolation<T>>, 
+    PhantomData<E>
+)
 where
 T: NetworkTranslation,
 E: NetworkMovement;
@@ -113,8 +135,29 @@ where
 T: NetworkTranslation,
 E: NetworkMovement {
     #[inline]
-    fn new() -> Self {
-        Self(PhantomData::<T>, PhantomData::<E>)
+    pub fn new() -> Self {
+        Self(None, PhantomData::<E>)
+    }
+} 
+
+impl<T, E> Default for NetworkTranslationPlugin<T, E>
+where 
+T: NetworkTranslation,
+E: NetworkMovement {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, E> NetworkTranslationPlugin<T, E>
+where 
+T: NetworkTranslation + HermiteInterpolatable,
+E: NetworkMovement {
+    /// interpolates with hermite on `InterpolationStrategy::Hermite`
+    #[inline]
+    pub fn hermite() -> Self {
+        Self(Some(HermiteInterpolation::new()), PhantomData::<E>)
     }
 } 
 
@@ -137,9 +180,20 @@ E: NetworkMovement {
                 .in_set(ServerBootSet::ApplyLocalChange)
             );   
         } else if app.world.contains_resource::<RepliconClient>() {
+            if let Some(hermite) = self.0 {
+                app.insert_resource(hermite);
+            }
+            if !app.is_plugin_added::<SmoothedVisualPlugin>() {
+                app.add_plugins(SmoothedVisualPlugin);
+            }
+
             app.add_systems(PreUpdate, (
-                handle_correct_translation::<T>,
-                apply_network_translation_system::<T>
+                handle_teleport_translation::<T>,
+                (
+                    handle_correct_translation::<T>,
+                    apply_network_translation_system::<T>
+                )
+            ).chain(
             ).in_set(ClientBootSet::ApplyReplication))
             .add_systems(PostUpdate, 
                 cache_translation_system::<T>
@@ -151,7 +205,10 @@ E: NetworkMovement {
     }
 }
 
-pub struct NetworkRotationPlugin<R, E>(PhantomData<R>, PhantomData<E>)
+pub struct NetworkRotationPlugin<R, E>(
+    Option<HermiteInterpolation<R>>, 
+    PhantomData<E>
+)
 where 
 R: NetworkRotation,
 E: NetworkMovement;
@@ -161,8 +218,29 @@ where
 R: NetworkRotation,
 E: NetworkMovement {
     #[inline]
-    fn new() -> Self {
-        Self(PhantomData::<R>, PhantomData::<E>)
+    pub fn new() -> Self {
+        Self(None, PhantomData::<E>)
+    }
+} 
+
+impl<R, E> Default for NetworkRotationPlugin<R, E>
+where 
+R: NetworkRotation,
+E: NetworkMovement {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, E> NetworkRotationPlugin<R, E>
+where 
+R: NetworkRotation + HermiteInterpolatable,
+E: NetworkMovement {
+    /// interpolates with hermite on `InterpolationStrategy::Hermite`
+    #[inline]
+    pub fn hermite() -> Self {
+        Self(Some(HermiteInterpolation::new()), PhantomData::<E>)
     }
 } 
 
@@ -185,9 +263,20 @@ E: NetworkMovement {
                 .in_set(ServerBootSet::ApplyLocalChange)
             );
         } else if app.world.contains_resource::<RepliconClient>() {
+            if let Some(hermite) = self.0 {
+                app.insert_resource(hermite);
+            }
+            if !app.is_plugin_added::<SmoothedVisualPlugin>() {
+                app.add_plugins(SmoothedVisualPlugin);
+            }
+
             app.add_systems(PreUpdate, (
-                handle_correct_rotation::<R>,
-                apply_network_rotation_system::<R>
+                handle_teleport_rotation::<R>,
+                (
+                    handle_correct_rotation::<R>,
+                    apply_network_rotation_system::<R>
+                )
+            ).chain(
             ).in_set(ClientBootSet::ApplyReplication))
             .add_systems(PostUpdate,
                 cache_rotation_system::<R>
@@ -199,6 +288,162 @@ E: NetworkMovement {
     }
 }
 
+pub struct NetworkRollbackPlugin<T, R, E, S>(
+    PhantomData<T>, 
+    PhantomData<R>, 
+    PhantomData<E>, 
+    PhantomData<S>
+)
+where 
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement,
+S: MovementStep<E>;
+
+impl<T, R, E, S> NetworkRollbackPlugin<T, R, E, S>
+where 
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement,
+S: MovementStep<E> {
+    #[inline]
+    pub fn new() -> Self {
+        Self(
+            PhantomData::<T>, 
+            PhantomData::<R>, 
+            PhantomData::<E>, 
+            PhantomData::<S>
+        )
+    }
+}
+
+impl<T, R, E, S> Default for NetworkRollbackPlugin<T, R, E, S>
+where 
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement,
+S: MovementStep<E> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R, E, S> Plugin for NetworkRollbackPlugin<T, R, E, S>
+where 
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement,
+S: MovementStep<E> {
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<RepliconServer>() {
+            // server is authority, nothing to roll back
+        } else if app.world.contains_resource::<RepliconClient>() {
+            app.add_systems(PreUpdate, 
+                rollback_system::<T, R, E, S>
+                .after(handle_correct_translation::<T>)
+                .after(handle_correct_rotation::<R>)
+                .in_set(ClientBootSet::ApplyReplication)
+            );
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+/// handles `Teleport` sent by server game logic
+pub struct NetworkTeleportPlugin<T, R, E>(
+    PhantomData<T>,
+    PhantomData<R>,
+    PhantomData<E>
+)
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement;
+
+impl<T, R, E> NetworkTeleportPlugin<T, R, E>
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement {
+    #[inline]
+    pub fn new() -> Self {
+        Self(
+            PhantomData::<T>,
+            PhantomData::<R>,
+            PhantomData::<E>
+        )
+    }
+}
+
+impl<T, R, E> Default for NetworkTeleportPlugin<T, R, E>
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R, E> Plugin for NetworkTeleportPlugin<T, R, E>
+where
+T: NetworkTranslation,
+R: NetworkRotation,
+E: NetworkMovement {
+    fn build(&self, app: &mut App) {
+        app.add_mapped_server_event::<Teleported<E>>(ChannelKind::Ordered);
+
+        if app.world.contains_resource::<RepliconServer>() {
+            app.add_event::<Teleport>()
+            .add_systems(PostUpdate,
+                teleport_system::<T, R, E>
+                .before(ServerBootSet::ApplyLocalChange)
+            );
+        } else if app.world.contains_resource::<RepliconClient>() {
+            app.add_systems(PreUpdate,
+                handle_teleported::<E>
+                .in_set(ClientBootSet::UnboxReplication)
+            );
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+/// replicates `Parent` of replicated entities as `NetworkParent`,
+/// network transforms of children are parent relative
+pub struct NetworkHierarchyPlugin;
+
+impl Plugin for NetworkHierarchyPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate_mapped::<NetworkParent>();
+
+        if app.world.contains_resource::<RepliconServer>() {
+            app.add_systems(PostUpdate, (
+                store_network_parent_system,
+                store_network_parent_removal_system
+            ).in_set(ServerBootSet::ApplyLocalChange))
+            .add_systems(PostUpdate,
+                culling::parent_visibility::inherit_parent_visibility_system
+                .after(ServerBootSet::Grouping)
+                .after(ServerBootSet::ApplyLocalChange)
+                .before(ServerSet::Send)
+            );
+        } else if app.world.contains_resource::<RepliconClient>() {
+            // before teleport handling reads parent relative transform
+            app.add_systems(PreUpdate,
+                sync_network_parent_system
+                .in_set(ClientBootSet::UnboxReplication)
+            );
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
 pub struct ClientEventPlugin<E: NetworkEvent>{
     pub channel_kind: ChannelKind,
     phantom: PhantomData<E>
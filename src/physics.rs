@@ -1,3 +1,5 @@
 pub mod rapier3d;
+pub mod lag_compensation;
 
 pub use rapier3d::*;
+pub use lag_compensation::*;
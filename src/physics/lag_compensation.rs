@@ -0,0 +1,405 @@
+use std::marker::PhantomData;
+use bevy::prelude::*;
+use bevy_rapier3d::{
+    prelude::*,
+    rapier::math::{Isometry, Real}
+};
+use bevy_replicon::prelude::*;
+use crate::{
+    core::*,
+    snapshot::ComponentSnapshots
+};
+
+#[derive(Clone)]
+pub enum LagCompensatedCast {
+    /// closest hit along the ray
+    Ray {
+        origin: Vec3,
+        direction: Vec3,
+        max_toi: f32
+    },
+    /// closest hit of the shape moved along direction
+    Shape {
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat,
+        direction: Vec3,
+        max_toi: f32
+    },
+    /// every collider intersecting the shape
+    Overlap {
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat
+    }
+}
+
+/// sent by server game logic to cast in the world seen by the shooter
+#[derive(Event, Clone)]
+pub struct LagCompensatedQuery {
+    pub shooter: Entity,
+    pub client_id: ClientId,
+    /// timestamp of the shooter's event in server clock
+    pub timestamp: f64,
+    pub cast: LagCompensatedCast
+}
+
+#[derive(Event, Clone)]
+pub struct LagCompensatedHit {
+    pub shooter: Entity,
+    pub target: Entity,
+    /// timestamp the world was rewound to
+    pub view_timestamp: f64,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub toi: f32
+}
+
+/// timestamp of the world rendered by the shooter on the event,
+/// remote entities are rendered behind the latest snapshot 
+/// which is half rtt old
+#[inline]
+pub fn view_timestamp(timestamp: f64, rtt: f64, render_delay: f64) -> f64 {
+    timestamp - rtt * 0.5 - render_delay
+}
+
+/// collider position at view timestamp, 
+/// snapshots are of the entity and offset is of the collider from it
+fn rewound_isometry<T, R>(
+    translation_snaps: &ComponentSnapshots<T>,
+    rotation_snaps: Option<&ComponentSnapshots<R>>,
+    current: &Isometry<Real>,
+    offset: Option<&Isometry<Real>>,
+    axis: &TransformAxis,
+    view_timestamp: f64
+) -> Option<Isometry<Real>>
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    let offset = offset.copied()
+    .unwrap_or_else(Isometry::identity);
+    let translation = match translation_snaps.bracketing(view_timestamp) {
+        Some((lower, upper, per)) => lower.component()
+        .linear_interpolate(upper.component(), per)
+        .to_vec3(axis.translation),
+        None => {
+            let oldest = translation_snaps.cache_get(0)
+            .or(translation_snaps.frontier_front())?;
+            // newer than history, entity is already there
+            if view_timestamp >= oldest.timestamp() {
+                return None;
+            }
+
+            debug!("view timestamp: {view_timestamp} is older than history, using oldest");
+            oldest.component().to_vec3(axis.translation)
+        }
+    };
+
+    let rotation = match rotation_snaps.and_then(|s| s.bracketing(view_timestamp)) {
+        Some((lower, upper, per)) => lower.component()
+        .linear_interpolate(upper.component(), per)
+        .to_quat(axis.rotation),
+        None => (current * offset.inverse()).rotation.into()
+    };
+
+    Some(Isometry::from_parts(translation.into(), rotation.into()) * offset)
+}
+
+fn cast(
+    context: &RapierContext,
+    query: &LagCompensatedQuery,
+    view_timestamp: f64,
+    hits: &mut EventWriter<LagCompensatedHit>
+) {
+    let filter = QueryFilter::default()
+    .exclude_sensors()
+    .exclude_collider(query.shooter);
+
+    match &query.cast {
+        LagCompensatedCast::Ray { origin, direction, max_toi } => {
+            if let Some((target, hit)) = context.cast_ray_and_get_normal(
+                *origin,
+                *direction,
+                *max_toi,
+                true,
+                filter
+            ) {
+                hits.send(LagCompensatedHit {
+                    shooter: query.shooter,
+                    target,
+                    view_timestamp,
+                    point: hit.point,
+                    normal: hit.normal,
+                    toi: hit.time_of_impact
+                });
+            }
+        }
+        LagCompensatedCast::Shape { shape, origin, rotation, direction, max_toi } => {
+            let options = ShapeCastOptions::with_max_time_of_impact(*max_toi);
+            if let Some((target, hit)) = context.cast_shape(
+                *origin,
+                *rotation,
+                *direction,
+                shape,
+                options,
+                filter
+            ) {
+                let (point, normal) = match hit.details {
+                    Some(d) => (d.witness1, d.normal1),
+                    None => (*origin + *direction * hit.time_of_impact, Vec3::ZERO)
+                };
+
+                hits.send(LagCompensatedHit {
+                    shooter: query.shooter,
+                    target,
+                    view_timestamp,
+                    point,
+                    normal,
+                    toi: hit.time_of_impact
+                });
+            }
+        }
+        LagCompensatedCast::Overlap { shape, origin, rotation } => {
+            context.intersections_with_shape(
+                *origin,
+                *rotation,
+                shape,
+                filter,
+                |target| {
+                    hits.send(LagCompensatedHit {
+                        shooter: query.shooter,
+                        target,
+                        view_timestamp,
+                        point: *origin,
+                        normal: Vec3::ZERO,
+                        toi: 0.0
+                    });
+                    true
+                }
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn lag_compensation_system<T, R>(
+    query: Query<(
+        Entity,
+        &ComponentSnapshots<T>,
+        Option<&ComponentSnapshots<R>>
+    ),
+        With<Collider>
+    >,
+    mut queries: EventReader<LagCompensatedQuery>,
+    mut hits: EventWriter<LagCompensatedHit>,
+    mut rejections: EventWriter<RewindRejected>,
+    mut context: ResMut<RapierContext>,
+    clock_sync: Option<Res<ClockSyncMap>>,
+    axis: Res<TransformAxis>,
+    config: Res<InterpolationConfig>,
+    max_rewind: Res<MaxRewind>,
+    clock: Res<NetworkClock>
+)
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let mut rewinds = vec![];
+
+    for q in queries.read() {
+        let sync = clock_sync.as_ref()
+        .and_then(|c| c.get(&q.client_id));
+        let rtt = match sync {
+            Some(s) => s.rtt(),
+            None => 0.0
+        };
+        // adaptive delay is reported by client, config has only the lower bound
+        let render_delay = match sync.and_then(|s| s.render_delay()) {
+            Some(d) => d,
+            None => config.render_delay()
+        };
+        let view_timestamp = match max_rewind.apply(
+            q.client_id,
+            view_timestamp(q.timestamp, rtt, render_delay),
+            now,
+            config.network_tick_delta,
+            &mut rejections
+        ) {
+            Some(t) => t,
+            None => continue
+        };
+        rewinds.push((view_timestamp, q));
+    }
+
+    if rewinds.is_empty() {
+        return;
+    }
+
+    // queries at same view timestamp share one rewind
+    rewinds.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut originals = vec![];
+    for (entity, translation_snaps, rotation_snaps) in query.iter() {
+        let handle = match context.entity2collider().get(&entity) {
+            Some(h) => *h,
+            None => continue
+        };
+        let collider = match context.colliders.get(handle) {
+            Some(c) => c,
+            None => continue
+        };
+
+        originals.push((
+            handle,
+            *collider.position(),
+            collider.position_wrt_parent().copied(),
+            translation_snaps,
+            rotation_snaps
+        ));
+    }
+
+    let mut rewound_at = None;
+    for (view_timestamp, q) in rewinds {
+        if rewound_at != Some(view_timestamp) {
+            for (handle, current, offset, translation_snaps, rotation_snaps) in originals.iter() {
+                let iso = rewound_isometry(
+                    translation_snaps,
+                    *rotation_snaps,
+                    current,
+                    offset.as_ref(),
+                    &axis,
+                    view_timestamp
+                ).unwrap_or(*current);
+
+                if let Some(c) = context.colliders.get_mut(*handle) {
+                    c.set_position(iso);
+                }
+            }
+
+            context.update_query_pipeline();
+            rewound_at = Some(view_timestamp);
+        }
+
+        cast(&context, q, view_timestamp, &mut hits);
+    }
+
+    for (handle, current, ..) in originals {
+        if let Some(c) = context.colliders.get_mut(handle) {
+            c.set_position(current);
+        }
+    }
+    context.update_query_pipeline();
+}
+
+pub struct LagCompensationPlugin<T, R>
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    phantom_t: PhantomData<T>,
+    phantom_r: PhantomData<R>
+}
+
+impl<T, R> LagCompensationPlugin<T, R>
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            phantom_t: PhantomData::<T>,
+            phantom_r: PhantomData::<R>
+        }
+    }
+}
+
+impl<T, R> Default for LagCompensationPlugin<T, R>
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R> Plugin for LagCompensationPlugin<T, R>
+where
+    T: NetworkTranslation,
+    R: NetworkRotation
+{
+    fn build(&self, app: &mut App) {
+        if app.world.contains_resource::<RepliconServer>() {
+            app.add_event::<LagCompensatedQuery>()
+            .add_event::<LagCompensatedHit>()
+            .add_systems(Update,
+                lag_compensation_system::<T, R>
+                .in_set(ServerBootSet::LagCompensation)
+            );
+        } else if app.world.contains_resource::<RepliconClient>() {
+            // server authoritative
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::control::{NetworkAngle, NetworkCharacterController};
+    use super::*;
+
+    #[test]
+    fn rewound_isometry_interpolates_history() {
+        let axis = TransformAxis::default();
+        let mut snaps = ComponentSnapshots::with_init(
+            NetworkCharacterController::from_vec3(Vec3::ZERO, axis.translation), 
+            0, 
+            1.0, 
+            8
+        ).unwrap();
+        snaps.insert(
+            NetworkCharacterController::from_vec3(Vec3::X * 2.0, axis.translation), 
+            1, 
+            2.0
+        ).unwrap();
+        let current = Isometry::from_parts(
+            (Vec3::X * 3.0).into(), 
+            Quat::IDENTITY.into()
+        );
+
+        let iso = rewound_isometry::<_, NetworkAngle>(
+            &snaps, None, &current, None, &axis, 1.5
+        ).unwrap();
+        assert_eq!(Vec3::from(iso.translation), Vec3::X);
+
+        let iso = rewound_isometry::<_, NetworkAngle>(
+            &snaps, None, &current, None, &axis, 0.5
+        ).unwrap();
+        assert_eq!(Vec3::from(iso.translation), Vec3::ZERO);
+
+        assert!(rewound_isometry::<_, NetworkAngle>(
+            &snaps, None, &current, None, &axis, 2.5
+        ).is_none());
+
+        // collider keeps its offset from the entity
+        let offset = Isometry::from_parts(
+            Vec3::Y.into(), 
+            Quat::IDENTITY.into()
+        );
+        let iso = rewound_isometry::<_, NetworkAngle>(
+            &snaps, None, &(current * offset), Some(&offset), &axis, 1.5
+        ).unwrap();
+        assert_eq!(Vec3::from(iso.translation), Vec3::X + Vec3::Y);
+    }
+}
@@ -1,14 +1,23 @@
 pub mod component_snapshot;
 pub mod event_snapshot;
+pub mod interpolated;
 
 use std::marker::PhantomData;
 use serde::{Serialize, de::DeserializeOwned};
 use bevy::prelude::*;
 use bevy_replicon::prelude::*;
-use crate::{ClientBootSet, NetworkEvent, ServerBootSet};
+use crate::{
+    ClientBootSet, 
+    HermiteInterpolatable, 
+    LinearInterpolatable, 
+    NetworkClock, 
+    NetworkEvent, 
+    ServerBootSet
+};
 
 pub use component_snapshot::*;
 pub use event_snapshot::*;
+pub use interpolated::*;
 
 pub struct EventSnapshotPlugin<E: NetworkEvent>(PhantomData<E>);
 
@@ -21,6 +30,8 @@ impl<E: NetworkEvent> EventSnapshotPlugin<E> {
 
 impl<E: NetworkEvent> Plugin for EventSnapshotPlugin<E> {
     fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkClock>();
+
         if app.world.contains_resource::<RepliconServer>() {
             app.add_systems(PreUpdate, 
                 server_populate_client_event_snapshots::<E>
@@ -50,6 +61,8 @@ where C: Component + Serialize + DeserializeOwned + Clone {
 impl<C> Plugin for ComponentSnapshotPlugin<C>
 where C: Component + Serialize + DeserializeOwned + Clone {
     fn build(&self, app: &mut App) {
+        app.init_resource::<NetworkClock>();
+
         if app.world.contains_resource::<RepliconServer>() {
             app.add_systems(PostUpdate,
                 server_populate_component_snapshots::<C>
@@ -65,3 +78,103 @@ where C: Component + Serialize + DeserializeOwned + Clone {
         }
     }
 }
+
+pub struct InputAckPlugin<E: NetworkEvent>(PhantomData<E>);
+
+impl<E: NetworkEvent> InputAckPlugin<E> {
+    #[inline]
+    pub fn new() -> Self {
+        Self(PhantomData::<E>)
+    }
+}
+
+impl<E: NetworkEvent> Default for InputAckPlugin<E> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: NetworkEvent> Plugin for InputAckPlugin<E> {
+    fn build(&self, app: &mut App) {
+        // ack is sent once per index, it must not be lost
+        app.add_server_event::<InputAck<E>>(ChannelKind::Ordered);
+
+        if app.world.contains_resource::<RepliconServer>() {
+            app.add_systems(PostUpdate, 
+                server_send_input_ack::<E>
+                .after(ServerPlugin::increment_tick)
+                .in_set(ServerBootSet::Cache)
+            );
+        } else if app.world.contains_resource::<RepliconClient>() {
+            app.add_systems(PreUpdate, 
+                client_handle_input_ack::<E>
+                .in_set(ClientBootSet::UnboxReplication)
+            );
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
+
+/// replicates and smooths any continuous component on clients,
+/// read it from `Interpolated<C>`
+pub struct NetworkInterpolationPlugin<C>
+where C: LinearInterpolatable + Serialize + DeserializeOwned {
+    pub cache_size: usize,
+    hermite: Option<HermiteInterpolation<C>>
+}
+
+impl<C> NetworkInterpolationPlugin<C>
+where C: LinearInterpolatable + Serialize + DeserializeOwned {
+    #[inline]
+    pub fn new(cache_size: usize) -> Self {
+        Self {
+            cache_size,
+            hermite: None
+        }
+    }
+}
+
+impl<C> NetworkInterpolationPlugin<C>
+where C: HermiteInterpolatable + Serialize + DeserializeOwned {
+    /// interpolates with hermite on `InterpolationStrategy::Hermite`
+    #[inline]
+    pub fn hermite(cache_size: usize) -> Self {
+        Self {
+            cache_size,
+            hermite: Some(HermiteInterpolation::new())
+        }
+    }
+}
+
+impl<C> Plugin for NetworkInterpolationPlugin<C>
+where C: LinearInterpolatable + Serialize + DeserializeOwned {
+    fn build(&self, app: &mut App) {
+        app.replicate::<C>()
+        .add_plugins(ComponentSnapshotPlugin::<C>::new());
+
+        if app.world.contains_resource::<RepliconServer>() {
+            // server has authoritative value
+        } else if app.world.contains_resource::<RepliconClient>() {
+            if let Some(hermite) = self.hermite {
+                app.insert_resource(hermite);
+            }
+
+            app.insert_resource(InterpolatedCacheSize::<C> {
+                cache_size: self.cache_size,
+                phantom: PhantomData::<C>
+            })
+            .add_systems(PreUpdate, 
+                init_interpolated_system::<C>
+                .in_set(ClientBootSet::UnboxReplication)
+            )
+            .add_systems(PreUpdate, (
+                apply_owning_interpolated_system::<C>,
+                apply_interpolated_system::<C>
+            ).in_set(ClientBootSet::ApplyReplication));
+        } else {
+            panic!("could not find replicon server nor client");
+        }
+    }
+}
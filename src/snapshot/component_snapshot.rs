@@ -1,14 +1,22 @@
+use std::collections::{vec_deque::Iter, VecDeque};
 use serde::{Deserialize, Serialize};
 use anyhow::bail;
-use bevy::{
-    prelude::*,
-    utils::SystemTime
-};
+use bevy::prelude::*;
 use bevy_replicon::{
     client::confirm_history, 
     server::server_tick::ServerTick 
 };
-use crate::core::LinearInterpolatable;
+use crate::core::{
+    render_tick,
+    HermiteInterpolatable, 
+    InterpolationConfig, 
+    InterpolationMode, 
+    InterpolationStrategy, 
+    LinearInterpolatable, 
+    NetworkClock,
+    RenderDelay,
+    ServerTickEstimate
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct ComponentSnapshot<C: Component> {
@@ -43,28 +51,37 @@ impl<C: Component> ComponentSnapshot<C> {
     }
 }
 
+/// extra slots over cache size so that frontier can grow
+/// without evicting itself before it is cached
+pub const FRONTIER_HEADROOM: usize = 64;
+
+/// fixed capacity ring buffer ordered by tick.
+/// front `cache_len` snapshots are cache, rest of them are frontier.
 #[derive(Component, Deserialize, Serialize)]
 pub struct ComponentSnapshots<C: Component> {
-    frontier: Vec<ComponentSnapshot<C>>,
-    cache: Vec<ComponentSnapshot<C>>,
-    cache_size: usize
+    buffer: VecDeque<ComponentSnapshot<C>>,
+    cache_len: usize,
+    cache_size: usize,
+    capacity: usize
 }
 
 impl<C: Component> ComponentSnapshots<C> {
     #[inline]
     pub fn with_capacity(cache_size: usize) -> Self {
+        let capacity = cache_size + FRONTIER_HEADROOM;
         Self{
-            frontier: Vec::new(),
-            cache: Vec::with_capacity(cache_size),
-            cache_size
+            buffer: VecDeque::with_capacity(capacity),
+            cache_len: 0,
+            cache_size,
+            capacity
         }
     }
 
     #[inline]
-    pub fn with_init(init: C, tick: u32, cache_size: usize) 
+    pub fn with_init(init: C, tick: u32, timestamp: f64, cache_size: usize) 
     -> anyhow::Result::<Self> {
         let mut snaps = Self::with_capacity(cache_size);
-        match snaps.insert(init, tick) {
+        match snaps.insert(init, tick, timestamp) {
             Ok(()) => Ok(snaps),
             Err(e) => Err(e) 
         }
@@ -72,65 +89,184 @@ impl<C: Component> ComponentSnapshots<C> {
 
     #[inline]
     pub fn frontier_len(&self) -> usize {
-        self.frontier.len()
+        self.buffer.len() - self.cache_len
     }
 
     #[inline]
     pub fn cache_len(&self) -> usize {
-        self.cache.len()
+        self.cache_len
     }
 
     #[inline]
     pub fn frontier_back(&self) -> Option<&ComponentSnapshot<C>> {
-        let len = self.frontier_len();
-        if len == 0 {
+        if self.frontier_len() == 0 {
             return None;
         }
 
-        self.frontier.get(len - 1)
+        self.buffer.back()
+    }
+
+    /// latest snapshot in both frontier and cache
+    #[inline]
+    pub fn latest(&self) -> Option<&ComponentSnapshot<C>> {
+        self.buffer.back()
     }
 
     #[inline]
     pub fn frontier_front(&self) -> Option<&ComponentSnapshot<C>> {
-        self.frontier.get(0)
+        self.buffer.get(self.cache_len)
     }
 
     #[inline]
-    pub fn frontier_ref(&self) -> &Vec<ComponentSnapshot<C>> {
-        &self.frontier
+    pub fn frontier_get(&self, index: usize) -> Option<&ComponentSnapshot<C>> {
+        self.buffer.get(self.cache_len + index)
     }
 
     #[inline]
-    pub fn cache_ref(&self) -> &Vec<ComponentSnapshot<C>> {
-        &self.cache
+    pub fn cache_get(&self, index: usize) -> Option<&ComponentSnapshot<C>> {
+        if index >= self.cache_len {
+            return None;
+        }
+
+        self.buffer.get(index)
     }
 
-    pub fn insert(&mut self, component: C, tick: u32) 
-    -> anyhow::Result<()> {
-        if self.cache_size > 0
-        && self.frontier_len() > self.cache_size {
-            warn!(
-                "frontier len: {}, call cache() after frontier_ref()",
-                self.frontier_len()
-            );
+    #[inline]
+    pub fn frontier_iter(&self) -> Iter<'_, ComponentSnapshot<C>> {
+        self.buffer.range(self.cache_len..)
+    }
+
+    #[inline]
+    pub fn cache_iter(&self) -> Iter<'_, ComponentSnapshot<C>> {
+        self.buffer.range(..self.cache_len)
+    }
+
+    /// latest snapshot stamped with exactly this tick
+    pub fn at_tick(&self, tick: u32) -> Option<&ComponentSnapshot<C>> {
+        let idx = self.buffer.partition_point(|s| s.tick() <= tick);
+        if idx == 0 {
+            return None;
         }
 
-        let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs_f64();
+        // idx is in range
+        let snap = &self.buffer[idx - 1];
+        if snap.tick() == tick {
+            Some(snap)
+        } else {
+            None
+        }
+    }
+
+    /// latest snapshot whose timestamp is smaller than or equal timestamp
+    pub fn latest_before(&self, timestamp: f64) -> Option<&ComponentSnapshot<C>> {
+        let idx = self.buffer.partition_point(|s| s.timestamp() <= timestamp);
+        if idx == 0 {
+            return None;
+        }
 
-        if let Some(frontier_snap) = self.frontier_front() {
-            if tick < frontier_snap.tick {
+        self.buffer.get(idx - 1)
+    }
+
+    /// pair of snapshots around timestamp and blend factor 0.0 ~ 1.0
+    /// between them, None if timestamp is out of history
+    pub fn bracketing(&self, timestamp: f64) 
+    -> Option<(&ComponentSnapshot<C>, &ComponentSnapshot<C>, f32)> {
+        let idx = self.buffer.partition_point(|s| s.timestamp() <= timestamp);
+        if idx == 0 {
+            return None;
+        }
+
+        // idx is in range
+        let lower = &self.buffer[idx - 1];
+        let upper = match self.buffer.get(idx) {
+            Some(s) => s,
+            None => {
+                if lower.timestamp() == timestamp {
+                    return Some((lower, lower, 0.0));
+                }
+                return None;
+            }
+        };
+
+        let span = upper.timestamp() - lower.timestamp();
+        let per = if span > 0.0 {
+            ((timestamp - lower.timestamp()) / span) as f32
+        } else {
+            0.0
+        };
+        Some((lower, upper, per))
+    }
+
+    /// pair of snapshots around fractional tick and blend factor 0.0 ~ 1.0
+    /// between them, None if tick is out of history
+    pub fn bracketing_tick(&self, tick: f64) 
+    -> Option<(&ComponentSnapshot<C>, &ComponentSnapshot<C>, f32)> {
+        let idx = self.buffer.partition_point(|s| s.tick() as f64 <= tick);
+        if idx == 0 {
+            return None;
+        }
+
+        // idx is in range
+        let lower = &self.buffer[idx - 1];
+        let upper = match self.buffer.get(idx) {
+            Some(s) => s,
+            None => {
+                if lower.tick() as f64 == tick {
+                    return Some((lower, lower, 0.0));
+                }
+                return None;
+            }
+        };
+
+        // ticks are ordered, span is more than 0 
+        // unless multiple snapshots are stamped with same tick
+        let span = (upper.tick() - lower.tick()) as f64;
+        let per = if span > 0.0 {
+            ((tick - lower.tick() as f64) / span) as f32
+        } else {
+            0.0
+        };
+        Some((lower, upper, per))
+    }
+
+    pub fn insert(&mut self, component: C, tick: u32, mut timestamp: f64) 
+    -> anyhow::Result<()> {
+        if !timestamp.is_finite() {
+            bail!("timestamp: {timestamp} is not finite");
+        }
+
+        if let Some(latest_snap) = self.buffer.back() {
+            if tick < latest_snap.tick {
                 bail!(
-                    "tick: {tick} is older than frontier snapshot: {}", 
-                    frontier_snap.tick
+                    "tick: {tick} is older than latest snapshot: {}", 
+                    latest_snap.tick
                 );
             }
 
-            debug_assert!(timestamp >= frontier_snap.timestamp());
+            // clock can go backward on real time clock,
+            // keep history ordered instead of discarding
+            if timestamp < latest_snap.timestamp() {
+                warn!(
+                    "timestamp: {timestamp} is older than latest snapshot: {}, clamping",
+                    latest_snap.timestamp()
+                );
+                timestamp = latest_snap.timestamp();
+            }
+        }
+
+        if self.buffer.len() >= self.capacity {
+            if self.cache_len == 0 {
+                warn!(
+                    "frontier len: {}, evicting oldest frontier, call cache() after frontier_iter()",
+                    self.frontier_len()
+                );
+            } else {
+                self.cache_len -= 1;
+            }
+            self.buffer.pop_front();
         }
         
-        self.frontier.push(ComponentSnapshot::new(
+        self.buffer.push_back(ComponentSnapshot::new(
             component, 
             timestamp, 
             tick
@@ -140,20 +276,39 @@ impl<C: Component> ComponentSnapshots<C> {
 
     #[inline]
     pub fn sort_frontier_by_timestamp(&mut self) {
-        if self.frontier_len() == 0 {
+        if self.frontier_len() < 2 {
+            return;
+        }
+
+        // timestamp is always stamped in insert()
+        // that returns error on bad result
+        let is_sorted = self.frontier_iter()
+        .zip(self.frontier_iter().skip(1))
+        .all(|(l, r)| l.timestamp() <= r.timestamp());
+        if is_sorted {
             return;
         }
 
-        self.frontier
+        let cache_len = self.cache_len;
+        self.buffer.make_contiguous()[cache_len..]
         .sort_unstable_by(|l, r| 
-            // timestamp is always stamped in insert()
-            // that returns error on bad result
             l.timestamp()
             .partial_cmp(&r.timestamp())
             .expect("timestamp is Nan")
         );
     }
 
+    /// drops every snapshot but the latest, 
+    /// that is left in frontier
+    #[inline]
+    pub fn reset_to_latest(&mut self) {
+        let len = self.buffer.len();
+        if len > 1 {
+            self.buffer.drain(..len - 1);
+        }
+        self.cache_len = 0;
+    }
+
     pub fn cache_n(&mut self, n: usize) {
         if n == 0 {
             return;
@@ -164,68 +319,91 @@ impl<C: Component> ComponentSnapshots<C> {
             return;
         }
 
-        if self.cache_size == 0 {
-            self.frontier.drain(..n);
-            return;
-        }
-
-        if n > self.cache_size {
-            self.cache.clear();
-            let uncacheable = n - self.cache_size;
-            self.frontier.drain(..uncacheable);
-            let drain = self.frontier.drain(..self.cache_size);
-            self.cache.append(&mut drain.collect());
-
-            debug_assert!(self.frontier_len() == frontier_len - n);
-            debug_assert!(self.cache_len() == self.cache_size);
-            return;
-        }
-
-        if self.cache_len() + n > self.cache_size {
-            self.cache.drain(..n);
+        self.cache_len += n;
+        while self.cache_len > self.cache_size {
+            self.buffer.pop_front();
+            self.cache_len -= 1;
         }
 
-        let drain = self.frontier.drain(..n);
-        self.cache.append(&mut drain.collect());
-
         debug_assert!(self.frontier_len() == frontier_len - n);
         debug_assert!(self.cache_len() <= self.cache_size);
     }
 
+    #[inline]
     pub fn cache(&mut self) {
-        let mut frontier_len = self.frontier_len();
-        if frontier_len == 0 {
-            return;
-        }
-
-        if self.cache_size == 0 {
-            self.frontier.clear();
-            return;
-        }
-
-        if frontier_len > self.cache_size {
-            let uncacheable = frontier_len - self.cache_size;
-            self.frontier.drain(..uncacheable);
-            self.cache.clear();
-            frontier_len = self.frontier_len();
-        }
+        self.cache_n(self.frontier_len());
+    }
+}
 
-        if self.cache_len() + frontier_len > self.cache_size {
-            self.cache.drain(..frontier_len);
-        }
+/// blends from -> to with optional sample before from and after to,
+/// paired with its tick span relative to from -> to
+type Blend<'a, C> = &'a dyn Fn(Option<(&C, f32)>, &C, &C, Option<(&C, f32)>, f32) -> C;
+
+#[inline]
+fn linear_blend<C: LinearInterpolatable>(
+    _: Option<(&C, f32)>, 
+    from: &C, 
+    to: &C, 
+    _: Option<(&C, f32)>, 
+    per: f32
+) -> C {
+    from.linear_interpolate(to, per)
+}
 
-        let drain = self.frontier.drain(..);
-        self.cache.append(&mut drain.collect());
+#[inline]
+fn hermite_blend<C: HermiteInterpolatable>(
+    prev: Option<(&C, f32)>, 
+    from: &C, 
+    to: &C, 
+    next: Option<(&C, f32)>, 
+    per: f32
+) -> C {
+    // mirror missing samples so that tangent follows the segment
+    let (prev, prev_span) = match prev {
+        Some((p, span)) => (p.clone(), span),
+        None => (from.linear_interpolate(to, -1.0), 1.0)
+    };
+    let (next, next_span) = match next {
+        Some((n, span)) => (n.clone(), span),
+        None => (to.linear_interpolate(from, -1.0), 1.0)
+    };
+    C::hermite_interpolate(&prev, from, to, &next, prev_span, next_span, per)
+}
 
-        debug_assert!(self.frontier_len() == 0);
-        debug_assert!(self.cache_len() <= self.cache_size);
+/// ticks of outer segment relative to ticks of inner segment
+#[inline]
+fn span_ratio(outer: u32, inner: u32) -> f32 {
+    if inner == 0 {
+        1.0
+    } else {
+        outer as f32 / inner as f32
     }
 }
 
 pub fn linear_interpolate_by_time<C: LinearInterpolatable>(
     snaps: &ComponentSnapshots<C>,
-    network_tick_delta: f64
+    config: &InterpolationConfig,
+    now: f64
+) -> anyhow::Result<Option<C>> {
+    interpolate_by_time(snaps, config, now, &linear_blend)
+}
+
+/// C1 continuous over snapshots, extrapolation stays linear
+pub fn hermite_interpolate_by_time<C: HermiteInterpolatable>(
+    snaps: &ComponentSnapshots<C>,
+    config: &InterpolationConfig,
+    now: f64
+) -> anyhow::Result<Option<C>> {
+    interpolate_by_time(snaps, config, now, &hermite_blend)
+}
+
+fn interpolate_by_time<C: LinearInterpolatable>(
+    snaps: &ComponentSnapshots<C>,
+    config: &InterpolationConfig,
+    now: f64,
+    blend: Blend<C>
 ) -> anyhow::Result<Option<C>> {
+    let network_tick_delta = config.network_tick_delta;
     if network_tick_delta <= 0.0 {
         bail!("invalid network tick delta");
     }
@@ -234,15 +412,12 @@ pub fn linear_interpolate_by_time<C: LinearInterpolatable>(
         return Ok(None)
     }
 
-    let mut iter = snaps.frontier_ref()
-    .iter()
+    let mut iter = snaps.frontier_iter()
     .rev();
     // frontier is longer than or equal 2
     let latest = iter.next().unwrap();
+    let second = iter.next().unwrap();
     
-    let now = SystemTime::now()
-    .duration_since(SystemTime::UNIX_EPOCH)?
-    .as_secs_f64();
     let elapsed = now - latest.timestamp();
     if elapsed < 0.0 {
         bail!("latest snapshot is future");
@@ -250,37 +425,266 @@ pub fn linear_interpolate_by_time<C: LinearInterpolatable>(
     
     // network tick delta time = 100%
     // elapsed = ?%
-    // into 0.0 ~ 1.0
+    // into 0.0 ~ max per
+    let max_per = match config.mode {
+        InterpolationMode::Interpolate => {
+            if elapsed >= network_tick_delta {
+                return Ok(Some(
+                    latest.component()
+                    .clone()
+                ));
+            }
+            1.0
+        }
+        InterpolationMode::Extrapolate { max_time } => {
+            1.0 + max_time.max(0.0) / network_tick_delta
+        }
+    };
+    let per = (elapsed / network_tick_delta).min(max_per) as f32;
 
-    // become 1.0 or over
-      // if we don't return here this can be extrapolation.
-      // but we are not sure should do or not 
-    if elapsed >= network_tick_delta {
+    // over 1.0, extrapolate along second -> latest
+    if per > 1.0 {
         return Ok(Some(
-            latest.component()
-            .clone()
+            second.component()
+            .linear_interpolate(latest.component(), per)
         ));
     }
-    
-    let per = (elapsed / network_tick_delta) as f32;
-    let second = iter.next().unwrap();
 
-    let interpolated = second
-    .component()
-    .linear_interpolate(latest.component(), per);
+    // second could be extrapolated when latest arrived,
+    // start from there so that it converges without snapping back
+    let third = iter.next()
+    .or(snaps.cache_iter().last());
+    let arrival_per = ((latest.timestamp() - second.timestamp()) / network_tick_delta)
+    .min(max_per) as f32;
+    let interpolated = match third {
+        Some(t) if arrival_per > 1.0 => {
+            let from = t.component()
+            .linear_interpolate(second.component(), arrival_per);
+            blend(None, &from, latest.component(), None, per)
+        }
+        _ => {
+            let span = latest.tick().saturating_sub(second.tick());
+            let prev = third.map(|t| (
+                t.component(), 
+                span_ratio(second.tick().saturating_sub(t.tick()), span)
+            ));
+            blend(prev, second.component(), latest.component(), None, per)
+        }
+    };
     Ok(Some(interpolated))
 }
 
+/// interpolates at fractional server tick over whole history,
+/// over the latest snapshot it stays or extrapolates by mode
+pub fn linear_interpolate_by_tick<C: LinearInterpolatable>(
+    snaps: &ComponentSnapshots<C>,
+    config: &InterpolationConfig,
+    render_tick: f64
+) -> anyhow::Result<Option<C>> {
+    interpolate_by_tick(snaps, config, render_tick, &linear_blend)
+}
+
+/// C1 continuous over snapshots, extrapolation stays linear
+pub fn hermite_interpolate_by_tick<C: HermiteInterpolatable>(
+    snaps: &ComponentSnapshots<C>,
+    config: &InterpolationConfig,
+    render_tick: f64
+) -> anyhow::Result<Option<C>> {
+    interpolate_by_tick(snaps, config, render_tick, &hermite_blend)
+}
+
+fn interpolate_by_tick<C: LinearInterpolatable>(
+    snaps: &ComponentSnapshots<C>,
+    config: &InterpolationConfig,
+    render_tick: f64,
+    blend: Blend<C>
+) -> anyhow::Result<Option<C>> {
+    if config.network_tick_delta <= 0.0 {
+        bail!("invalid network tick delta");
+    }
+
+    let idx = snaps.buffer.partition_point(|s| s.tick() as f64 <= render_tick);
+    if idx > 0 {
+        let lower = &snaps.buffer[idx - 1];
+        if let Some(upper) = snaps.buffer.get(idx) {
+            // ticks are ordered, span is more than 0 
+            // unless multiple snapshots are stamped with same tick
+            let span = (upper.tick() - lower.tick()) as f64;
+            let per = if span > 0.0 {
+                ((render_tick - lower.tick() as f64) / span) as f32
+            } else {
+                0.0
+            };
+            let ticks = upper.tick() - lower.tick();
+            let prev = idx.checked_sub(2)
+            .and_then(|i| snaps.buffer.get(i))
+            .map(|s| (
+                s.component(), 
+                span_ratio(lower.tick() - s.tick(), ticks)
+            ));
+            let next = snaps.buffer.get(idx + 1)
+            .map(|s| (
+                s.component(), 
+                span_ratio(s.tick() - upper.tick(), ticks)
+            ));
+
+            return Ok(Some(
+                blend(prev, lower.component(), upper.component(), next, per)
+            ));
+        }
+    }
+
+    let mut iter = snaps.buffer.iter()
+    .rev();
+    let latest = match iter.next() {
+        Some(s) => s,
+        None => return Ok(None)
+    };
+
+    // older than history
+    if render_tick < latest.tick() as f64 {
+        return Ok(snaps.buffer.front()
+            .map(|s| s.component().clone())
+        );
+    }
+
+    let max_ticks = match config.mode {
+        InterpolationMode::Interpolate => 0.0,
+        InterpolationMode::Extrapolate { max_time } => {
+            max_time.max(0.0) / config.network_tick_delta
+        }
+    };
+    let second = match iter.next() {
+        Some(s) if s.tick() < latest.tick() && max_ticks > 0.0 => s,
+        _ => return Ok(Some(latest.component().clone()))
+    };
+
+    let span = (latest.tick() - second.tick()) as f64;
+    let over = (render_tick - latest.tick() as f64).min(max_ticks);
+    let per = (1.0 + over / span) as f32;
+    Ok(Some(
+        second.component()
+        .linear_interpolate(latest.component(), per)
+    ))
+}
+
+type Interpolate<C> = fn(
+    &ComponentSnapshots<C>, 
+    &InterpolationConfig, 
+    f64
+) -> anyhow::Result<Option<C>>;
+
+/// hermite interpolation by tick and by time,
+/// only constructible for components implementing `HermiteInterpolatable`
+#[derive(Resource)]
+pub(crate) struct HermiteInterpolation<C: LinearInterpolatable> {
+    by_tick: Interpolate<C>,
+    by_time: Interpolate<C>
+}
+
+impl<C: LinearInterpolatable> Clone for HermiteInterpolation<C> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: LinearInterpolatable> Copy for HermiteInterpolation<C> {}
+
+impl<C: HermiteInterpolatable> HermiteInterpolation<C> {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self { 
+            by_tick: hermite_interpolate_by_tick::<C>, 
+            by_time: hermite_interpolate_by_time::<C> 
+        }
+    }
+}
+
+/// interpolation of remote snapshots in a frame,
+/// shared by all systems applying them on client
+pub(crate) struct FrameInterpolation<'a, C: LinearInterpolatable> {
+    config: &'a InterpolationConfig,
+    by_tick: Interpolate<C>,
+    by_time: Interpolate<C>,
+    render_tick: Option<f64>,
+    now: f64
+}
+
+impl<'a, C: LinearInterpolatable> FrameInterpolation<'a, C> {
+    /// strategy falls back to linear unless registered with hermite,
+    /// timeline falls back to receive time until server tick is estimated
+    pub(crate) fn new(
+        config: &'a InterpolationConfig,
+        hermite: Option<&HermiteInterpolation<C>>,
+        estimate: Option<&ServerTickEstimate>,
+        render_delay: Option<&RenderDelay>,
+        now: f64
+    ) -> Self {
+        let (by_tick, by_time): (Interpolate<C>, Interpolate<C>) = match (config.strategy, hermite) {
+            (InterpolationStrategy::Hermite, Some(h)) => (h.by_tick, h.by_time),
+            _ => (linear_interpolate_by_tick::<C>, linear_interpolate_by_time::<C>)
+        };
+
+        Self {
+            config,
+            by_tick,
+            by_time,
+            render_tick: render_tick(config, estimate, render_delay, now),
+            now
+        }
+    }
+
+    /// value to render, None keeps the current one.
+    /// latest is rendered if interpolation fails.
+    /// a pair of snapshots is left in frontier, the rest is cached
+    pub(crate) fn apply(&self, snaps: &mut ComponentSnapshots<C>, latest: &C) -> Option<C> {
+        const REQUIRED: usize = 2;
+
+        snaps.sort_frontier_by_timestamp();
+        let interpolated = match self.render_tick {
+            Some(tick) => (self.by_tick)(snaps, self.config, tick),
+            None => (self.by_time)(snaps, self.config, self.now)
+        };
+        let value = match interpolated {
+            Ok(v) => v,
+            Err(e) => {
+                if cfg!(debug_assertions) {
+                    panic!("error on interpolation: {e}");
+                } else {
+                    error!("error on interpolation: {e}");
+                    snaps.cache();
+                    Some(latest.clone())
+                }
+            }
+        };
+
+        let len = snaps.frontier_len();
+        if len > REQUIRED {
+            snaps.cache_n(len - REQUIRED);
+        }
+        value
+    }
+}
+
 pub(super) fn server_populate_component_snapshots<C: Component + Clone>(
     mut query: Query<
         (&C, &mut ComponentSnapshots<C>), 
         Changed<C>
     >,
-    server_tick: Res<ServerTick>
+    server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>
 ) { 
     let tick = server_tick.get();
+    let timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
     for (c, mut snaps) in query.iter_mut() {
-        match snaps.insert(c.clone(), tick) {
+        match snaps.insert(c.clone(), tick, timestamp) {
             Ok(()) => debug!(
                 "inserted component snapshot: frontier len: {}, cache len: {}",
                 snaps.frontier_len(),
@@ -299,10 +703,18 @@ pub(super) fn client_populate_component_snapshots<C: Component + Clone>(
     ), 
         Changed<C>
     >,
+    clock: Res<NetworkClock>
 ) {
+    let timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
     for (c, mut snaps, confirmed_tick) in query.iter_mut() {
         let tick = confirmed_tick.last_tick().get();
-        match snaps.insert(c.clone(), tick) {
+        match snaps.insert(c.clone(), tick, timestamp) {
             Ok(()) => debug!(
                 "inserted component snapshot: frontier len: {}, cache len: {}",
                 snaps.frontier_len(),
@@ -312,3 +724,173 @@ pub(super) fn client_populate_component_snapshots<C: Component + Clone>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::InterpolationTimeline;
+    use super::*;
+
+    #[derive(Component, Clone, Copy, PartialEq, Debug)]
+    struct Value(f32);
+
+    impl LinearInterpolatable for Value {
+        fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+            Self(self.0 + (rhs.0 - self.0) * per)
+        }
+    }
+
+    fn ticks(iter: Iter<'_, ComponentSnapshot<Value>>) -> Vec<u32> {
+        iter.map(|s| s.tick()).collect()
+    }
+
+    fn filled(cache_size: usize, ticks: std::ops::Range<u32>, clock: &mut NetworkClock)
+    -> ComponentSnapshots<Value> {
+        let mut snaps = ComponentSnapshots::with_capacity(cache_size);
+        for tick in ticks {
+            snaps.insert(Value(tick as f32), tick, clock.now().unwrap()).unwrap();
+            clock.advance(0.1);
+        }
+        snaps
+    }
+
+    #[test]
+    fn cache_keeps_latest_within_cache_size() {
+        let mut clock = NetworkClock::manual(0.0);
+        let mut snaps = filled(3, 0..5, &mut clock);
+        assert_eq!(snaps.frontier_len(), 5);
+
+        snaps.cache_n(2);
+        assert_eq!(ticks(snaps.cache_iter()), vec![0, 1]);
+        assert_eq!(ticks(snaps.frontier_iter()), vec![2, 3, 4]);
+
+        snaps.cache();
+        assert_eq!(snaps.frontier_len(), 0);
+        assert_eq!(ticks(snaps.cache_iter()), vec![2, 3, 4]);
+        assert_eq!(snaps.cache_get(0).unwrap().component(), &Value(2.0));
+        assert!(snaps.cache_get(3).is_none());
+    }
+
+    #[test]
+    fn reset_to_latest_drops_history() {
+        let mut clock = NetworkClock::manual(0.0);
+        let mut snaps = filled(3, 0..5, &mut clock);
+        snaps.cache_n(2);
+
+        snaps.reset_to_latest();
+        assert_eq!(snaps.cache_len(), 0);
+        assert_eq!(ticks(snaps.frontier_iter()), vec![4]);
+    }
+
+    #[test]
+    fn insert_on_full_buffer_evicts_cache_first() {
+        let mut clock = NetworkClock::manual(0.0);
+        let mut snaps = filled(2, 0..2, &mut clock);
+        snaps.cache();
+
+        for tick in 2..(2 + FRONTIER_HEADROOM as u32) {
+            snaps.insert(Value(0.0), tick, clock.now().unwrap()).unwrap();
+        }
+        assert_eq!(ticks(snaps.cache_iter()), vec![0, 1]);
+
+        snaps.insert(Value(0.0), 100, clock.now().unwrap()).unwrap();
+        assert_eq!(ticks(snaps.cache_iter()), vec![1]);
+        assert_eq!(snaps.frontier_len(), FRONTIER_HEADROOM + 1);
+        assert_eq!(snaps.frontier_back().unwrap().tick(), 100);
+    }
+
+    #[test]
+    fn lookup_by_tick_and_timestamp() {
+        let mut clock = NetworkClock::manual(10.0);
+        let mut snaps = filled(8, 0..4, &mut clock);
+        snaps.cache_n(2);
+
+        assert_eq!(snaps.at_tick(2).unwrap().component(), &Value(2.0));
+        assert!(snaps.at_tick(4).is_none());
+
+        assert_eq!(snaps.latest_before(10.15).unwrap().tick(), 1);
+        assert_eq!(snaps.latest_before(10.3).unwrap().tick(), 3);
+        assert!(snaps.latest_before(9.0).is_none());
+
+        let (lower, upper, per) = snaps.bracketing(10.125).unwrap();
+        assert_eq!((lower.tick(), upper.tick()), (1, 2));
+        assert!((per - 0.25).abs() < 1e-4);
+        assert!(snaps.bracketing(9.0).is_none());
+        assert!(snaps.bracketing(11.0).is_none());
+    }
+
+    #[test]
+    fn insert_rejects_older_tick_and_clamps_older_timestamp() {
+        let mut snaps = ComponentSnapshots::with_capacity(2);
+        snaps.insert(Value(0.0), 5, 1.0).unwrap();
+        assert!(snaps.insert(Value(1.0), 4, 2.0).is_err());
+        assert!(snaps.insert(Value(1.0), 6, f64::NAN).is_err());
+        assert_eq!(snaps.frontier_len(), 1);
+
+        snaps.insert(Value(1.0), 6, 0.5).unwrap();
+        assert_eq!(snaps.frontier_back().unwrap().timestamp(), 1.0);
+    }
+
+    #[test]
+    fn interpolate_with_manual_clock() {
+        let mut clock = NetworkClock::manual(0.0);
+        let snaps = filled(2, 0..2, &mut clock);
+        assert_eq!(snaps.frontier_back().unwrap().timestamp(), 0.1);
+
+        let config = InterpolationConfig {
+            network_tick_delta: 0.1,
+            mode: InterpolationMode::Interpolate,
+            timeline: default(),
+            strategy: default()
+        };
+        let value = linear_interpolate_by_time(&snaps, &config, 0.15).unwrap().unwrap();
+        assert!((value.0 - 0.5).abs() < 1e-4);
+
+        let value = linear_interpolate_by_time(&snaps, &config, 0.5).unwrap().unwrap();
+        assert_eq!(value, Value(1.0));
+        assert!(linear_interpolate_by_time(&snaps, &config, 0.0).is_err());
+    }
+
+    #[test]
+    fn extrapolate_up_to_max_time_and_converge() {
+        let mut clock = NetworkClock::manual(0.0);
+        let mut snaps = filled(2, 0..2, &mut clock);
+        let config = InterpolationConfig {
+            network_tick_delta: 0.1,
+            mode: InterpolationMode::Extrapolate { max_time: 0.1 },
+            timeline: default(),
+            strategy: default()
+        };
+
+        let value = linear_interpolate_by_time(&snaps, &config, 0.25).unwrap().unwrap();
+        assert!((value.0 - 1.5).abs() < 1e-4);
+        let value = linear_interpolate_by_time(&snaps, &config, 0.5).unwrap().unwrap();
+        assert!((value.0 - 2.0).abs() < 1e-4);
+
+        // late snapshot arrives while extrapolated to 1.5
+        snaps.insert(Value(1.2), 2, 0.25).unwrap();
+        let value = linear_interpolate_by_time(&snaps, &config, 0.25).unwrap().unwrap();
+        assert!((value.0 - 1.5).abs() < 1e-4);
+        let value = linear_interpolate_by_time(&snaps, &config, 0.35).unwrap().unwrap();
+        assert!((value.0 - 1.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn interpolate_by_tick_over_history() {
+        let mut clock = NetworkClock::manual(0.0);
+        let mut snaps = filled(4, 0..4, &mut clock);
+        snaps.cache_n(2);
+        let config = InterpolationConfig {
+            network_tick_delta: 0.1,
+            mode: InterpolationMode::Extrapolate { max_time: 0.1 },
+            timeline: InterpolationTimeline::ServerTick { delay_ticks: 2 },
+            strategy: default()
+        };
+
+        let value = linear_interpolate_by_tick(&snaps, &config, 0.5).unwrap().unwrap();
+        assert!((value.0 - 0.5).abs() < 1e-4);
+        let value = linear_interpolate_by_tick(&snaps, &config, 3.5).unwrap().unwrap();
+        assert!((value.0 - 3.5).abs() < 1e-4);
+        let value = linear_interpolate_by_tick(&snaps, &config, 10.0).unwrap().unwrap();
+        assert!((value.0 - 4.0).abs() < 1e-4);
+    }
+}
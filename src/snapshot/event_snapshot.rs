@@ -1,8 +1,7 @@
+use std::{collections::VecDeque, marker::PhantomData};
+use serde::{Serialize, Deserialize};
 use anyhow::bail;
-use bevy::{
-    utils::SystemTime,
-    prelude::*
-};
+use bevy::prelude::*;
 use bevy_replicon::{
     client::confirm_history::ConfirmHistory,
     server::server_tick::ServerTick, 
@@ -10,20 +9,25 @@ use bevy_replicon::{
 };
 use crate::{
     Owning, 
-    core::{NetworkEntity, NetworkEvent}
+    core::{
+        ClockSyncMap, InterpolationConfig, MaxRewind, 
+        NetworkClock, NetworkEntity, NetworkEvent, RewindRejected
+    }
 };
 
 pub struct EventSnapshot<E: NetworkEvent> {
     event: E,
+    timestamp: f64,
     received_timestamp: f64,
     tick: u32
 }
 
 impl<E: NetworkEvent> EventSnapshot<E> {
     #[inline]
-    pub fn new(event: E, received_timestamp: f64, tick: u32) -> Self {
+    pub fn new(event: E, timestamp: f64, received_timestamp: f64, tick: u32) -> Self {
         Self{
             event,
+            timestamp,
             received_timestamp,
             tick
         }
@@ -49,18 +53,42 @@ impl<E: NetworkEvent> EventSnapshot<E> {
         self.event.index()
     }
 
+    /// event timestamp converted into local clock
     #[inline]
     pub fn timestamp(&self) -> f64 {
-        self.event.timestamp()
+        self.timestamp
     } 
 }
 
+/// sent by server to owner client when events are consumed.
+/// state replicated at tick or later includes events up to index
+#[derive(Event, Serialize, Deserialize)]
+pub struct InputAck<E: NetworkEvent> {
+    pub index: usize,
+    pub tick: u32,
+    phantom: PhantomData<E>
+}
+
+impl<E: NetworkEvent> InputAck<E> {
+    #[inline]
+    pub fn new(index: usize, tick: u32) -> Self {
+        Self {
+            index,
+            tick,
+            phantom: PhantomData::<E>
+        }
+    }
+}
+
+pub const ACK_HISTORY_SIZE: usize = 32;
+
 #[derive(Component)]
 pub struct EventSnapshots<E: NetworkEvent> {
     frontier: Vec<EventSnapshot<E>>,
     frontier_index: usize,
     cache: Vec<EventSnapshot<E>>,
     cache_size: usize,
+    acks: VecDeque<(u32, usize)>
 }
 
 impl<E: NetworkEvent> EventSnapshots<E> {
@@ -70,7 +98,8 @@ impl<E: NetworkEvent> EventSnapshots<E> {
             frontier: Vec::new(),
             frontier_index: 0,
             cache: Vec::with_capacity(cache_size),
-            cache_size
+            cache_size,
+            acks: VecDeque::with_capacity(ACK_HISTORY_SIZE)
         }
     }
 
@@ -114,8 +143,132 @@ impl<E: NetworkEvent> EventSnapshots<E> {
         &self.cache
     }
 
-    pub fn insert(&mut self, event: E, tick: u32)
-    -> anyhow::Result<()> {
+    /// latest acknowledged (tick, index)
+    #[inline]
+    pub fn latest_acknowledged(&self) -> Option<(u32, usize)> {
+        self.acks.back().copied()
+    }
+
+    /// index acknowledged for state replicated at tick
+    pub fn acknowledged_at(&self, tick: u32) -> Option<usize> {
+        let idx = self.acks.partition_point(|&(t, _)| t <= tick);
+        if idx == 0 {
+            return None;
+        }
+
+        // idx is in range
+        Some(self.acks[idx - 1].1)
+    }
+
+    /// records ack, older ack than latest one is ignored
+    pub fn acknowledge(&mut self, index: usize, tick: u32) {
+        if let Some((latest_tick, latest_index)) = self.latest_acknowledged() {
+            if index <= latest_index || tick < latest_tick {
+                return;
+            }
+        }
+
+        if self.acks.len() >= ACK_HISTORY_SIZE {
+            self.acks.pop_front();
+        }
+        self.acks.push_back((tick, index));
+    }
+
+    /// drops cached snapshots acknowledged up to index, 
+    /// they are never replayed again
+    pub fn prune_acknowledged(&mut self, index: usize) {
+        let idx = self.cache.partition_point(|s| s.index() <= index);
+        self.cache.drain(..idx);
+    }
+
+    /// drops snapshots up to index and rejects them if received later,
+    /// used when they are stale e.g. after teleport
+    pub fn discard_until(&mut self, index: usize) {
+        self.frontier.retain(|s| s.index() > index);
+        self.prune_acknowledged(index);
+        self.frontier_index = self.frontier_index.max(index + 1);
+    }
+
+    /// latest index received so far
+    #[inline]
+    pub fn latest_index(&self) -> Option<usize> {
+        let frontier_latest = self.frontier.iter()
+        .map(|s| s.index())
+        .max();
+        match (frontier_latest, self.frontier_index) {
+            (Some(i), _) => Some(i),
+            (None, 0) => None,
+            (None, i) => Some(i - 1)
+        }
+    }
+
+    /// cached snapshots ordered by event timestamp,
+    /// cache is ordered by index and timestamps can be out of order 
+    /// when they are converted by a new clock offset
+    fn cache_by_timestamp(&self) -> Vec<&EventSnapshot<E>> {
+        let mut sorted: Vec<&EventSnapshot<E>> = self.cache.iter()
+        .collect();
+        sorted.sort_by(|l, r| l.timestamp().total_cmp(&r.timestamp()));
+        sorted
+    }
+
+    /// latest cached snapshot received at exactly this tick
+    pub fn at_tick(&self, tick: u32) -> Option<&EventSnapshot<E>> {
+        self.cache.iter()
+        .rev()
+        .find(|s| s.tick() == tick)
+    }
+
+    /// latest cached snapshot whose event timestamp is smaller than or equal timestamp
+    pub fn latest_before(&self, timestamp: f64) -> Option<&EventSnapshot<E>> {
+        let sorted = self.cache_by_timestamp();
+        let idx = sorted.partition_point(|s| s.timestamp() <= timestamp);
+        if idx == 0 {
+            return None;
+        }
+
+        Some(sorted[idx - 1])
+    }
+
+    /// pair of cached snapshots around timestamp and blend factor 0.0 ~ 1.0
+    /// between them, None if timestamp is out of cache
+    pub fn bracketing(&self, timestamp: f64) 
+    -> Option<(&EventSnapshot<E>, &EventSnapshot<E>, f32)> {
+        let sorted = self.cache_by_timestamp();
+        let idx = sorted.partition_point(|s| s.timestamp() <= timestamp);
+        if idx == 0 {
+            return None;
+        }
+
+        // idx is in range
+        let lower = sorted[idx - 1];
+        let upper = match sorted.get(idx) {
+            Some(s) => *s,
+            None => {
+                if lower.timestamp() == timestamp {
+                    return Some((lower, lower, 0.0));
+                }
+                return None;
+            }
+        };
+
+        let span = upper.timestamp() - lower.timestamp();
+        let per = if span > 0.0 {
+            ((timestamp - lower.timestamp()) / span) as f32
+        } else {
+            0.0
+        };
+        Some((lower, upper, per))
+    }
+
+    /// timestamp is event timestamp converted into local clock
+    pub fn insert(
+        &mut self, 
+        event: E, 
+        tick: u32, 
+        timestamp: f64, 
+        received_timestamp: f64
+    ) -> anyhow::Result<()> {
         if self.cache_size > 0 
         && self.frontier_len() > self.cache_size {
             warn!(
@@ -124,11 +277,7 @@ impl<E: NetworkEvent> EventSnapshots<E> {
             );
         }
 
-        let received_timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs_f64();
-
-        if let Some(frontier_snap) = self.frontier_front() {
+        if let Some(frontier_snap) = self.frontier_back() {
             if tick < frontier_snap.tick {
                 bail!(
                     "tick: {tick} is older than frontier snapshot: {}", 
@@ -136,14 +285,6 @@ impl<E: NetworkEvent> EventSnapshots<E> {
                 );
             }
 
-            if event.timestamp() <= frontier_snap.timestamp() {
-                bail!(
-                    "timestamp: {} is older than latest: {}",
-                    event.timestamp(),
-                    frontier_snap.timestamp(),
-                );
-            }
-
             debug_assert!(received_timestamp >= frontier_snap.received_timestamp());
         }
 
@@ -156,6 +297,7 @@ impl<E: NetworkEvent> EventSnapshots<E> {
 
         self.frontier.push(EventSnapshot::new(
             event, 
+            timestamp,
             received_timestamp, 
             tick
         ));
@@ -189,10 +331,14 @@ impl<E: NetworkEvent> EventSnapshots<E> {
             frontier_len = self.frontier_len();
         }
         
-        if self.cache_len() + frontier_len > self.cache_size {
-            self.cache.drain(..frontier_len);
+        let cache_len = self.cache_len();
+        if cache_len + frontier_len > self.cache_size {
+            // cache can be shorter than frontier after discard_until()
+            self.cache.drain(..(cache_len + frontier_len - self.cache_size).min(cache_len));
         }
 
+        // cache stays ordered by index, frontier can arrive out of order
+        self.sort_frontier_by_index();
         // frontier is not empty
         let latest_idx = self.frontier_back()
         .unwrap()
@@ -206,24 +352,61 @@ impl<E: NetworkEvent> EventSnapshots<E> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn server_populate_client_event_snapshots<E: NetworkEvent>(
     mut events: EventReader<FromClient<E>>,
     mut query: Query<(&NetworkEntity, &mut EventSnapshots<E>)>,
-    server_tick: Res<ServerTick>
+    server_tick: Res<ServerTick>,
+    clock: Res<NetworkClock>,
+    clock_sync: Option<Res<ClockSyncMap>>,
+    max_rewind: Res<MaxRewind>,
+    interpolation_config: Res<InterpolationConfig>,
+    mut rejections: EventWriter<RewindRejected>
 ) {
     let tick = server_tick.get();
+    let received_timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
     for FromClient { client_id, event } in events.read() {
         if let Err(e) = event.validate() {
             warn!("discarding: {e}");
             continue;
         }
 
+        // history is in server clock, 
+        // client clock is not mixed in before it is sampled
+        let timestamp = match clock_sync {
+            Some(ref s) => match s.to_server_time(client_id, event.timestamp()) {
+                Some(t) => t,
+                None => {
+                    warn!("discarding: event from: {client_id:?} before clock sync");
+                    continue;
+                }
+            },
+            None => event.timestamp()
+        };
+        // window is applied once on receive, history stores the result
+        let timestamp = match max_rewind.apply(
+            *client_id,
+            timestamp,
+            received_timestamp,
+            interpolation_config.network_tick_delta,
+            &mut rejections
+        ) {
+            Some(t) => t,
+            None => continue
+        };
+
         for (net_e, mut snaps) in query.iter_mut() {
             if net_e.client_id() != *client_id {
                 continue;
             }
 
-            match snaps.insert(event.clone(), tick) {
+            match snaps.insert(event.clone(), tick, timestamp, received_timestamp) {
                 Ok(()) => debug!(
                     "inserted event snapshot: frontier index: {} frontier len: {}, cache len: {}",
                     snaps.frontier_index(),
@@ -244,7 +427,15 @@ pub(super) fn client_populate_client_event_snapshots<E: NetworkEvent>(
         With<Owning>
     >,
     mut events: EventReader<E>,
+    clock: Res<NetworkClock>
 ) {
+    let received_timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
     for event in events.read() {
         if let Err(e) = event.validate() {
             warn!("discarding: {e}");
@@ -253,7 +444,12 @@ pub(super) fn client_populate_client_event_snapshots<E: NetworkEvent>(
 
         for (mut snaps, confirmed_tick) in query.iter_mut() {
             let tick = confirmed_tick.last_tick().get();
-            match snaps.insert(event.clone(), tick) {
+            match snaps.insert(
+                event.clone(), 
+                tick, 
+                event.timestamp(), 
+                received_timestamp
+            ) {
                 Ok(()) => debug!(
                     "inserted event snapshot: frontier index: {} frontier len: {}, cache len: {}",
                     snaps.frontier_index(),
@@ -265,3 +461,163 @@ pub(super) fn client_populate_client_event_snapshots<E: NetworkEvent>(
         }
     }
 }
+
+pub(super) fn server_send_input_ack<E: NetworkEvent>(
+    mut query: Query<(&NetworkEntity, &mut EventSnapshots<E>)>,
+    mut acks: EventWriter<ToClients<InputAck<E>>>,
+    server_tick: Res<ServerTick>
+) {
+    // state including events consumed from now is replicated 
+    // at current tick if it is incremented on this frame, otherwise next tick
+    let tick = if server_tick.is_changed() {
+        server_tick.get()
+    } else {
+        server_tick.get().wrapping_add(1)
+    };
+
+    for (net_e, mut snaps) in query.iter_mut() {
+        let frontier_index = snaps.frontier_index();
+        if frontier_index == 0 {
+            continue;
+        }
+
+        let index = frontier_index - 1;
+        if let Some((_, latest_index)) = snaps.latest_acknowledged() {
+            if latest_index == index {
+                continue;
+            }
+        }
+
+        snaps.acknowledge(index, tick);
+        acks.send(ToClients {
+            mode: SendMode::Direct(net_e.client_id()),
+            event: InputAck::new(index, tick)
+        });
+    }
+}
+
+pub(super) fn client_handle_input_ack<E: NetworkEvent>(
+    mut query: Query<&mut EventSnapshots<E>, With<Owning>>,
+    mut acks: EventReader<InputAck<E>>
+) {
+    for ack in acks.read() {
+        for mut snaps in query.iter_mut() {
+            snaps.acknowledge(ack.index, ack.tick);
+            debug!("input acknowledged: index: {} tick: {}", ack.index, ack.tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Event, Serialize, Deserialize, Clone)]
+    struct Input {
+        index: usize,
+        timestamp: f64
+    }
+
+    impl NetworkEvent for Input {
+        fn index(&self) -> usize {
+            self.index
+        }
+
+        fn timestamp(&self) -> f64 {
+            self.timestamp
+        }
+
+        fn validate(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn acknowledged_inputs_are_looked_up_by_tick_and_pruned() {
+        let mut snaps = EventSnapshots::with_capacity(8);
+        for index in 0..5 {
+            let timestamp = index as f64;
+            snaps.insert(Input { index, timestamp }, 0, timestamp, timestamp).unwrap();
+        }
+        snaps.cache();
+
+        snaps.acknowledge(1, 10);
+        snaps.acknowledge(3, 12);
+        // older ack is ignored
+        snaps.acknowledge(2, 13);
+        assert_eq!(snaps.latest_acknowledged(), Some((12, 3)));
+        assert_eq!(snaps.acknowledged_at(9), None);
+        assert_eq!(snaps.acknowledged_at(11), Some(1));
+        assert_eq!(snaps.acknowledged_at(20), Some(3));
+
+        snaps.prune_acknowledged(1);
+        let indices: Vec<usize> = snaps.cache_ref()
+        .iter()
+        .map(|s| s.index())
+        .collect();
+        assert_eq!(indices, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn discarded_inputs_are_rejected() {
+        let mut snaps = EventSnapshots::with_capacity(8);
+        for index in 0..3 {
+            let timestamp = index as f64;
+            snaps.insert(Input { index, timestamp }, 0, timestamp, timestamp).unwrap();
+        }
+        snaps.cache();
+        snaps.insert(Input { index: 3, timestamp: 3.0 }, 0, 3.0, 3.0).unwrap();
+        assert_eq!(snaps.latest_index(), Some(3));
+
+        snaps.discard_until(3);
+        assert_eq!(snaps.frontier_len(), 0);
+        assert_eq!(snaps.cache_len(), 0);
+        assert!(snaps.insert(Input { index: 2, timestamp: 4.0 }, 1, 4.0, 4.0).is_err());
+        assert!(snaps.insert(Input { index: 4, timestamp: 4.0 }, 1, 4.0, 4.0).is_ok());
+    }
+
+    #[test]
+    fn cache_after_discard_keeps_capacity() {
+        let mut snaps = EventSnapshots::with_capacity(4);
+        for index in 0..4 {
+            let timestamp = index as f64;
+            snaps.insert(Input { index, timestamp }, 0, timestamp, timestamp).unwrap();
+        }
+        snaps.cache();
+        snaps.discard_until(2);
+        assert_eq!(snaps.cache_len(), 1);
+
+        for index in 4..8 {
+            let timestamp = index as f64;
+            snaps.insert(Input { index, timestamp }, 0, timestamp, timestamp).unwrap();
+        }
+        snaps.cache();
+        let indices: Vec<usize> = snaps.cache_ref()
+        .iter()
+        .map(|s| s.index())
+        .collect();
+        assert_eq!(indices, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn out_of_order_inputs_are_accepted_by_index() {
+        let mut snaps = EventSnapshots::with_capacity(8);
+        // index 1 arrives before 0, and 2 is stamped earlier by a new clock offset
+        snaps.insert(Input { index: 1, timestamp: 2.0 }, 0, 2.0, 2.0).unwrap();
+        snaps.insert(Input { index: 0, timestamp: 1.0 }, 0, 1.0, 2.1).unwrap();
+        snaps.insert(Input { index: 2, timestamp: 1.5 }, 1, 1.5, 2.2).unwrap();
+        snaps.cache();
+
+        let indices: Vec<usize> = snaps.cache_ref()
+        .iter()
+        .map(|s| s.index())
+        .collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert!(snaps.insert(Input { index: 2, timestamp: 3.0 }, 1, 3.0, 3.0).is_err());
+
+        assert_eq!(snaps.latest_before(1.7).map(|s| s.index()), Some(2));
+        let (lower, upper, per) = snaps.bracketing(1.75).unwrap();
+        assert_eq!((lower.index(), upper.index(), per), (2, 1, 0.5));
+        assert_eq!(snaps.at_tick(0).map(|s| s.index()), Some(1));
+    }
+}
@@ -0,0 +1,183 @@
+use std::marker::PhantomData;
+use bevy::prelude::*;
+use bevy_replicon::client::confirm_history::ConfirmHistory;
+use crate::core::*;
+use super::component_snapshot::*;
+
+/// client side smoothed value of replicated component,
+/// same as the component on owned entities
+#[derive(Component, Clone)]
+pub struct Interpolated<C: LinearInterpolatable>(C);
+
+impl<C: LinearInterpolatable> Interpolated<C> {
+    #[inline]
+    pub fn new(component: C) -> Self {
+        Self(component)
+    }
+
+    #[inline]
+    pub fn get(&self) -> &C {
+        &self.0
+    }
+}
+
+#[derive(Resource)]
+pub(super) struct InterpolatedCacheSize<C: LinearInterpolatable> {
+    pub(super) cache_size: usize,
+    pub(super) phantom: PhantomData<C>
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn init_interpolated_system<C: LinearInterpolatable>(
+    mut commands: Commands,
+    query: Query<(Entity, &C, &ConfirmHistory), (
+        Added<C>,
+        Without<ComponentSnapshots<C>>
+    )>,
+    cache_size: Res<InterpolatedCacheSize<C>>,
+    clock: Res<NetworkClock>
+) {
+    let timestamp = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+
+    for (entity, c, confirmed_tick) in query.iter() {
+        let snaps = match ComponentSnapshots::with_init(
+            c.clone(),
+            confirmed_tick.last_tick().get(),
+            timestamp,
+            cache_size.cache_size
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("failed to initialize snapshots for: {entity:?}: {e}");
+                continue;
+            }
+        };
+
+        commands.entity(entity)
+        .insert((snaps, Interpolated::new(c.clone())));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn apply_owning_interpolated_system<C: LinearInterpolatable>(
+    mut query: Query<
+        (&C, &mut ComponentSnapshots<C>, &mut Interpolated<C>),
+        (With<Owning>, Changed<C>)
+    >
+) {
+    for (c, mut snaps, mut interpolated) in query.iter_mut() {
+        snaps.cache();
+        interpolated.0 = c.clone();
+    }
+}
+
+/// interpolates by `InterpolationConfig` mode and timeline,
+/// strategy is hermite only if registered with `NetworkInterpolationPlugin::hermite`
+#[allow(clippy::type_complexity)]
+pub(super) fn apply_interpolated_system<C: LinearInterpolatable>(
+    mut query: Query<(
+        &C,
+        &mut ComponentSnapshots<C>,
+        &mut Interpolated<C>
+    ),
+        Without<Owning>
+    >,
+    config: Res<InterpolationConfig>,
+    hermite: Option<Res<HermiteInterpolation<C>>>,
+    estimate: Option<Res<ServerTickEstimate>>,
+    render_delay: Option<Res<RenderDelay>>,
+    clock: Res<NetworkClock>
+) {
+    let now = match clock.now() {
+        Ok(t) => t,
+        Err(e) => {
+            error!("could not get timestamp: {e}");
+            return;
+        }
+    };
+    let interpolation = FrameInterpolation::new(
+        &config,
+        hermite.as_deref(),
+        estimate.as_deref(),
+        render_delay.as_deref(),
+        now
+    );
+
+    for (c, mut snaps, mut interpolated) in query.iter_mut() {
+        if let Some(v) = interpolation.apply(&mut snaps, c) {
+            interpolated.0 = v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_replicon::core::replicon_tick::RepliconTick;
+    use super::*;
+
+    #[derive(Component, Clone, Copy, PartialEq, Debug)]
+    struct Value(f32);
+
+    impl LinearInterpolatable for Value {
+        fn linear_interpolate(&self, rhs: &Self, per: f32) -> Self {
+            Self(self.0 + (rhs.0 - self.0) * per)
+        }
+    }
+
+    #[test]
+    fn owned_is_copied_and_remote_is_interpolated() {
+        let mut world = World::new();
+        world.insert_resource(InterpolationConfig {
+            network_tick_delta: 0.5,
+            mode: InterpolationMode::Interpolate,
+            timeline: InterpolationTimeline::ReceiveTime,
+            strategy: InterpolationStrategy::Linear
+        });
+        world.insert_resource(InterpolatedCacheSize::<Value> {
+            cache_size: 8,
+            phantom: PhantomData
+        });
+        world.insert_resource(NetworkClock::manual(0.0));
+
+        let history = || ConfirmHistory::new(RepliconTick::new(1));
+        let owned = world.spawn((Value(0.0), history(), Owning)).id();
+        let remote = world.spawn((Value(0.0), history())).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            init_interpolated_system::<Value>,
+            apply_owning_interpolated_system::<Value>,
+            apply_interpolated_system::<Value>
+        ).chain());
+        schedule.run(&mut world);
+
+        let interpolated = |world: &World, e: Entity| world.get::<Interpolated<Value>>(e)
+        .unwrap()
+        .get()
+        .0;
+        for e in [owned, remote] {
+            assert!(world.get::<ComponentSnapshots<Value>>(e).is_some());
+            assert_eq!(interpolated(&world, e), 0.0);
+        }
+
+        // replicated at tick 2, remote is rendered a tick behind
+        for e in [owned, remote] {
+            world.get_mut::<Value>(e).unwrap().0 = 10.0;
+        }
+        world.get_mut::<ComponentSnapshots<Value>>(remote)
+        .unwrap()
+        .insert(Value(10.0), 2, 0.5)
+        .unwrap();
+        world.insert_resource(NetworkClock::manual(0.75));
+        schedule.run(&mut world);
+
+        assert_eq!(interpolated(&world, owned), 10.0);
+        assert_eq!(interpolated(&world, remote), 5.0);
+    }
+}